@@ -0,0 +1,136 @@
+//! Derives `MetaFactory` constructors for structs.
+//!
+//! `#[derive(MetaFactory)]` wires every struct field as a separate
+//! argument, so a struct that is only assembled from its dependencies
+//! does not need a `|a: A, b: B| Foo { a, b }` closure.
+//!
+//! The derive implements `metafactory::dependencies::Dependencies` for the
+//! struct and `ToMetaFactory` for its `Constructor`. The metafactory lists
+//! field types in `get_arg_types`, field names as argument names in
+//! `get_args`, and produces `Factory<Foo>` from `MetaFactory::new`.
+//!
+//! ```
+//! use metafactory::{ metafactory, argless_as_factory, cloneable, AsFactoryExt };
+//! use metafactory::dependencies::constructor;
+//! use metafactory_derive::MetaFactory;
+//!
+//! #[derive(MetaFactory)]
+//! struct Size {
+//!     width: i32,
+//!     height: i32,
+//! }
+//!
+//! fn main() {
+//!     let meta_size = metafactory(constructor::<Size>());
+//!
+//!     assert_eq!(meta_size.get_args()[1].name, Some("height".to_string()));
+//!
+//!     let size = meta_size.new(vec![
+//!         argless_as_factory(cloneable(3i32)),
+//!         argless_as_factory(cloneable(4i32)),
+//!     ]).ok().unwrap().as_factory_of::<Size>().unwrap().take();
+//!
+//!     assert_eq!(size.width * size.height, 12);
+//! }
+//! ```
+//!
+//! Fields of tuple structs are named by their index.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{ Span, TokenStream as TokenStream2 };
+use quote::quote;
+use syn::{ parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Ident, Index };
+
+/// Derive `Dependencies` for the struct and `ToMetaFactory` for its
+/// `Constructor`.
+#[proc_macro_derive(MetaFactory)]
+pub fn derive_metafactory(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => data.fields.clone(),
+        _ => return Err(Error::new_spanned(&input.ident, "MetaFactory can only be derived for structs")),
+    };
+
+    if let Some(lifetime) = input.generics.lifetimes().next() {
+        return Err(Error::new_spanned(lifetime, "MetaFactory can not be derived for structs with lifetimes"));
+    }
+
+    // Constructed values are stored in factories, so all type parameters
+    // must outlive them.
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!('static));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let arg_names: Vec<String> = fields.iter().enumerate()
+        .map(|(index, f)| match f.ident {
+            Some(ref ident) => ident.to_string(),
+            None => index.to_string(),
+        })
+        .collect();
+
+    let vars: Vec<_> = (0..types.len())
+        .map(|index| Ident::new(&format!("field_{}", index), Span::call_site()))
+        .collect();
+
+    let construct = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|f| &f.ident);
+            quote! { #name { #(#idents: #vars.take()),* } }
+        },
+        Fields::Unnamed(_) => {
+            let indexes = (0..types.len()).map(Index::from);
+            quote! { #name { #(#indexes: #vars.take()),* } }
+        },
+        Fields::Unit => quote! { #name },
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::metafactory::dependencies::Dependencies for #name #ty_generics #where_clause {
+            fn arg_types() -> ::std::vec::Vec<::metafactory::dependencies::TypeDef> {
+                vec![#(::metafactory::dependencies::TypeDef::of::<#types>()),*]
+            }
+
+            fn args() -> ::std::vec::Vec<::metafactory::ArgDescriptor> {
+                vec![#(::metafactory::ArgDescriptor::of::<#types>()),*]
+            }
+
+            #[allow(unused_variables)]
+            fn unwrap_factories(
+                factories: &mut ::metafactory::dependencies::FactoryList
+            ) -> ::std::result::Result<
+                ::metafactory::Factory<Self>,
+                ::metafactory::error::FactoryErrorKind
+            > {
+                #(
+                    let #vars = factories.unwrap::<#types>()?;
+                )*
+                ::std::result::Result::Ok(::metafactory::dependencies::assemble(move || #construct))
+            }
+        }
+
+        impl #impl_generics ::metafactory::ToMetaFactory<#name #ty_generics>
+            for ::metafactory::dependencies::Constructor<#name #ty_generics> #where_clause
+        {
+            fn to_metafactory(self) -> ::std::boxed::Box<dyn ::metafactory::MetaFactory> {
+                ::metafactory::annotated::Annotated::new(
+                    ::metafactory::dependencies::dependency_metafactory(|value: #name #ty_generics| value)
+                )
+                    .arg_names(&[#(#arg_names),*])
+                    .expect("struct fields have one unique name each")
+                    .build()
+            }
+        }
+    })
+}
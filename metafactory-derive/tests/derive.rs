@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use typedef::TypeDef;
+use metafactory::{ metafactory, argless_as_factory, cloneable, ArgDescriptor, AsFactoryExt };
+use metafactory::dependencies::constructor;
+use metafactory::error::{ FactoryErrorKind, ArgTypeMismatch };
+use metafactory_derive::MetaFactory;
+
+#[derive(MetaFactory)]
+struct Size {
+    width: i32,
+    height: i32,
+    unit: &'static str,
+}
+
+#[derive(MetaFactory)]
+struct Pair(bool, String);
+
+#[derive(MetaFactory)]
+struct Empty;
+
+#[derive(MetaFactory)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[test]
+fn should_list_fields_as_arguments() {
+    let meta = metafactory(constructor::<Size>());
+
+    assert_eq!(meta.get_type(), TypeDef::of::<Size>());
+    assert_eq!(meta.get_arg_types(), vec![
+        TypeDef::of::<i32>(),
+        TypeDef::of::<i32>(),
+        TypeDef::of::<&'static str>(),
+    ]);
+    assert_eq!(meta.get_args(), vec![
+        ArgDescriptor::required(TypeDef::of::<i32>()).with_name("width"),
+        ArgDescriptor::required(TypeDef::of::<i32>()).with_name("height"),
+        ArgDescriptor::required(TypeDef::of::<&'static str>()).with_name("unit"),
+    ]);
+}
+
+#[test]
+fn should_build_struct_from_argument_factories() {
+    let factory = metafactory(constructor::<Size>())
+        .new(vec![
+            argless_as_factory(cloneable(3i32)),
+            argless_as_factory(cloneable(4i32)),
+            argless_as_factory(cloneable("px")),
+        ]).ok().unwrap()
+        .as_factory_of::<Size>().unwrap();
+
+    let size = factory.clone().take();
+    assert_eq!((size.width, size.height, size.unit), (3, 4, "px"));
+}
+
+#[test]
+fn should_bind_fields_by_name() {
+    let mut args = HashMap::new();
+    args.insert("unit".to_string(), argless_as_factory(cloneable("em")));
+    args.insert("height".to_string(), argless_as_factory(cloneable(2i32)));
+    args.insert("width".to_string(), argless_as_factory(cloneable(1i32)));
+
+    let size = metafactory(constructor::<Size>())
+        .new_named(args).ok().unwrap()
+        .as_factory_of::<Size>().unwrap()
+        .take();
+
+    assert_eq!((size.width, size.height, size.unit), (1, 2, "em"));
+}
+
+#[test]
+fn should_name_field_in_errors() {
+    let error = metafactory(constructor::<Size>())
+        .new(vec![
+            argless_as_factory(cloneable(3i32)),
+            argless_as_factory(cloneable(true)),
+            argless_as_factory(cloneable("px")),
+        ]).err().unwrap();
+
+    assert_eq!(
+        error,
+        FactoryErrorKind::ArgTypeMismatch(ArgTypeMismatch {
+            argument_name: Some("height".to_string()),
+            ..ArgTypeMismatch::new(TypeDef::of::<i32>(), 1, TypeDef::of::<bool>())
+        })
+    );
+}
+
+#[test]
+fn should_derive_for_tuple_unit_and_generic_structs() {
+    let meta_pair = metafactory(constructor::<Pair>());
+    assert_eq!(meta_pair.get_args()[1].name, Some("1".to_string()));
+    let pair = meta_pair
+        .new(vec![
+            argless_as_factory(cloneable(true)),
+            argless_as_factory(cloneable("a".to_string())),
+        ]).ok().unwrap()
+        .as_factory_of::<Pair>().unwrap()
+        .take();
+    assert_eq!((pair.0, pair.1), (true, "a".to_string()));
+
+    let meta_empty = metafactory(constructor::<Empty>());
+    assert!(meta_empty.get_arg_types().is_empty());
+    assert!(meta_empty.new(Vec::new()).ok().unwrap().as_factory_of::<Empty>().is_some());
+
+    let meta_wrapper = metafactory(constructor::<Wrapper<u8>>());
+    assert_eq!(meta_wrapper.get_arg_types(), vec![TypeDef::of::<u8>()]);
+    let wrapper = meta_wrapper
+        .new(vec![argless_as_factory(cloneable(7u8))]).ok().unwrap()
+        .as_factory_of::<Wrapper<u8>>().unwrap()
+        .take();
+    assert_eq!(wrapper.inner, 7);
+}
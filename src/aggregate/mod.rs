@@ -1,10 +1,56 @@
 //! Implements a factory that aggregates the results of other factories of
 //! the same type.
+//!
+//! `Aggregate` collects the values into a `Vec<T>`. `MapAggregate` collects
+//! them into a map, under the key given for every child factory.
+//!
+//! `Aggregate::new_collect` makes an aggregate that collects values into any
+//! `FromIterator` container, for example `HashSet<T>` or `VecDeque<T>`.
+//!
+//! `Aggregate::new_iter` makes an aggregate that produces `AggregateIter<T>`
+//! instead, which builds child values only when the iterator is advanced.
+//!
+//! Children wrapped in `OrderedChild` can be given priorities and
+//! constraints relative to named siblings, so that the aggregate produces
+//! values in the same order no matter how children were registered.
+//!
+//! ```
+//! use metafactory::{ argless_as_factory, cloneable, AsFactoryExt };
+//! use metafactory::aggregate::{ Aggregate, OrderedChild };
+//!
+//! fn main() {
+//!     let middleware = Aggregate::new::<&'static str>()
+//!         .new_ordered_factory(vec![
+//!             OrderedChild::new(argless_as_factory(cloneable("handler"))).priority(-100),
+//!             OrderedChild::new(argless_as_factory(cloneable("auth"))).named("auth").after("log"),
+//!             OrderedChild::new(argless_as_factory(cloneable("log"))).named("log"),
+//!         ])
+//!         .ok().unwrap()
+//!         .as_factory_of::<Vec<&'static str>>().unwrap();
+//!
+//!     assert_eq!(middleware.take(), vec!["log", "auth", "handler"]);
+//! }
+//! ```
+
+use std::collections::{ HashMap, BTreeMap };
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::rc::Rc;
 
-use std::any::{ Any };
-use std::boxed::BoxAny;
 use typedef::TypeDef;
-use { Factory, Getter };
+use crate::{ AnyFactory, Factory, FactoryType, Getter };
+use crate::sync::{ SendFactory, SendGetter };
+use crate::fallible::{ TryFactory, TryGetter };
+use crate::error::{ FactoryErrorKind, ChildTypeMismatch, ChildKindMismatch, TryTakeError };
+use crate::AsFactoryExt;
+
+pub use self::ordered::OrderedChild;
+
+mod ordered;
+
+type NewFactoryFn<'a> = Box<dyn Fn(Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> + 'a>;
+type NewMapFactoryFn<'a, K> = Box<dyn Fn(Vec<(K, AnyFactory)>) -> Result<AnyFactory, FactoryErrorKind> + 'a>;
 
 /// Proxy for initializing aggregate factory without caring about the type used.
 ///
@@ -35,6 +81,7 @@ use { Factory, Getter };
 ///             true_metafactory.new(Vec::new()).ok().unwrap(),
 ///             false_metafactory.new(Vec::new()).ok().unwrap(),
 ///         ])
+///         .ok().unwrap()
 ///         .as_factory_of::<Vec<bool>>().unwrap();
 ///
 ///     assert_eq!(vec![true, false], true_and_false.take());
@@ -46,8 +93,7 @@ use { Factory, Getter };
 ///
 /// ```
 /// # extern crate metafactory;
-/// use std::any::Any;
-/// use metafactory::{ metafactory, argless_as_factory, AsFactoryExt };
+/// use metafactory::{ metafactory, argless_as_factory, cloneable, AnyFactory, AsFactoryExt };
 /// use metafactory::aggregate::Aggregate;
 ///
 /// fn main() {
@@ -56,19 +102,19 @@ use { Factory, Getter };
 ///     let mut aggregate = Aggregate::new::<bool>();
 ///
 ///     // Once we actually have our factories, we can inject them into aggregate
-///     // without dealing with types. Of course, we should make sure that types
-///     // actually match before doing that in the code that is using this
-///     // implementation.
+///     // without dealing with types. If some factory does not produce `bool`,
+///     // `new_factory` returns an error.
 ///     // Then we can call `new_factory` to convert all dynamic stuff to
 ///     // statically constructed call hierarchy:
 ///     let anyed_bool_array_factory = aggregate
 ///         .new_factory(vec![
 ///             argless_as_factory(|| true),
-///             argless_as_factory(true),
-///             argless_as_factory(|| 4i == 8),
-///         ]);
+///             argless_as_factory(cloneable(true)),
+///             argless_as_factory(|| 4 == 8),
+///         ])
+///         .ok().unwrap(); // check for errors here
 ///
-///     // Of course, that returns it anyed (`Box<Any>`), but we can easily get un-anyed version
+///     // Of course, that returns it anyed (`AnyFactory`), but we can easily get un-anyed version
 ///     // by downcasting to `Factory<Vec<bool>>` or using a convenience extension
 ///     // method for that:
 ///     let bool_array_factory = anyed_bool_array_factory
@@ -85,7 +131,7 @@ use { Factory, Getter };
 ///
 ///     // We can pass it when constructing a factory for this lambda metafactory:
 ///     let factory_all_true = metafactory_all_true.new(vec![
-///         box bool_array_factory.clone() as Box<Any>
+///         AnyFactory::from(bool_array_factory.clone())
 ///     ])
 ///         .ok().unwrap() // check for errors here
 ///         .as_factory_of::<bool>().unwrap() // same story with downcasting
@@ -97,7 +143,7 @@ use { Factory, Getter };
 pub struct Aggregate<'a> {
     typedef: TypeDef,
     container_typedef: TypeDef,
-    do_new: Box<Fn<(Vec<Box<Any>>,),Box<Any>> + 'a>,
+    do_new: NewFactoryFn<'a>,
 }
 
 impl<'a> Aggregate<'a> {
@@ -106,44 +152,410 @@ impl<'a> Aggregate<'a> {
         Aggregate {
             typedef: TypeDef::of::<T>(),
             container_typedef: TypeDef::of::<Vec<T>>(),
-            do_new: box |&: items: Vec<Box<Any>>| {
-                box Factory::<Vec<T>>::new(
-                    box AG::<T>::new(
-                        items.into_iter()
-                            .map(|i| *i.downcast::<Factory<T>>().ok().expect(
-                                format!("failed to downcast factory child to Factory<{}>", TypeDef::name_of::<T>()).as_slice()
-                            ))
-                            .collect()
-                    )
-                )
-            }
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::of::<T>(), index, i, |i| i.as_factory_of::<T>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(Factory::<Vec<T>>::new(
+                    Box::new(AG::<T>::new(factories))
+                )))
+            }),
+        }
+    }
+
+    /// Create new aggregate instance for specified type, that collects
+    /// values into container `C` instead of `Vec<T>`.
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    /// use metafactory::{ metafactory, argless_as_factory, cloneable, AsFactoryExt };
+    /// use metafactory::aggregate::Aggregate;
+    ///
+    /// fn main() {
+    ///     let tags = Aggregate::new_collect::<&'static str, BTreeSet<&'static str>>()
+    ///         .new_factory(vec![
+    ///             argless_as_factory(cloneable("b")),
+    ///             argless_as_factory(cloneable("a")),
+    ///             argless_as_factory(cloneable("b")),
+    ///         ])
+    ///         .ok().unwrap();
+    ///
+    ///     let joined = metafactory(|tags: BTreeSet<&'static str>| tags.into_iter().collect::<Vec<_>>().join(","))
+    ///         .new(vec![tags]).ok().unwrap()
+    ///         .as_factory_of::<String>().unwrap();
+    ///
+    ///     assert_eq!(joined.take(), "a,b");
+    /// }
+    /// ```
+    pub fn new_collect<T: 'static, C>() -> Aggregate<'a>
+        where C: 'static + FromIterator<T>
+    {
+        Aggregate {
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<C>(),
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::of::<T>(), index, i, |i| i.as_factory_of::<T>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(Factory::<C>::new(
+                    Box::new(CollectAG::<T, C> {
+                        factories,
+                        _marker: PhantomData,
+                    })
+                )))
+            }),
+        }
+    }
+
+    /// Create new aggregate instance for specified type, that produces
+    /// `AggregateIter<T>` instead of `Vec<T>`.
+    ///
+    /// Child values are built only when the iterator reaches them, so a
+    /// consumer that stops early does not build the rest.
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use std::cell::Cell;
+    /// use metafactory::{ argless_as_factory, AsFactoryExt };
+    /// use metafactory::aggregate::{ Aggregate, AggregateIter };
+    ///
+    /// fn main() {
+    ///     let built = Rc::new(Cell::new(0));
+    ///     let plugin = |name: &'static str| {
+    ///         let built = built.clone();
+    ///         argless_as_factory(move || { built.set(built.get() + 1); name })
+    ///     };
+    ///
+    ///     let plugins = Aggregate::new_iter::<&'static str>()
+    ///         .new_factory(vec![plugin("json"), plugin("yaml"), plugin("toml")])
+    ///         .ok().unwrap()
+    ///         .as_factory_of::<AggregateIter<&'static str>>().unwrap();
+    ///
+    ///     assert_eq!(plugins.take().find(|&p| p == "yaml"), Some("yaml"));
+    ///     assert_eq!(built.get(), 2);
+    /// }
+    /// ```
+    pub fn new_iter<T: 'static>() -> Aggregate<'a> {
+        Aggregate {
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<AggregateIter<T>>(),
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::of::<T>(), index, i, |i| i.as_factory_of::<T>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(Factory::<AggregateIter<T>>::new(
+                    Box::new(IterAG::<T> {
+                        factories: Rc::new(factories),
+                    })
+                )))
+            }),
+        }
+    }
+
+    /// Create new keyed aggregate instance for specified type, that
+    /// collects values into `HashMap<K, T>`.
+    pub fn new_map<K, T>() -> MapAggregate<'a, K>
+        where K: 'static + Clone + Eq + Hash, T: 'static
+    {
+        MapAggregate::new::<T, HashMap<K, T>>(new_map_fn::<K, T, HashMap<K, T>>())
+    }
+
+    /// Create new keyed aggregate instance for specified type, that
+    /// collects values into `BTreeMap<K, T>`.
+    pub fn new_btree_map<K, T>() -> MapAggregate<'a, K>
+        where K: 'static + Clone + Ord, T: 'static
+    {
+        MapAggregate::new::<T, BTreeMap<K, T>>(new_map_fn::<K, T, BTreeMap<K, T>>())
+    }
+
+    /// Create new keyed aggregate instance that produces `SendFactory`
+    /// of `HashMap<K, T>`.
+    ///
+    /// Child factories should also be `SendFactory` instances.
+    pub fn new_send_map<K, T>() -> MapAggregate<'a, K>
+        where K: 'static + Clone + Eq + Hash + Send + Sync, T: 'static
+    {
+        MapAggregate::new::<T, HashMap<K, T>>(Box::new(|items: Vec<(K, AnyFactory)>| {
+            let factories = unwrap_keyed_children(FactoryType::send_of::<T>(), items, |i| i.as_send_factory_of::<T>())?;
+            Ok(AnyFactory::from(SendFactory::<HashMap<K, T>>::new(
+                Box::new(SendMapAG::<K, T, HashMap<K, T>> {
+                    factories,
+                    _marker: PhantomData,
+                })
+            )))
+        }))
+    }
+
+    /// Create new keyed aggregate instance that produces `TryFactory`
+    /// of `HashMap<K, T>`.
+    ///
+    /// Child factories can be anything `as_try_factory_of` accepts.
+    pub fn new_try_map<K, T, E>() -> MapAggregate<'a, K>
+        where K: 'static + Clone + Eq + Hash, T: 'static, E: 'static
+    {
+        MapAggregate::new::<T, HashMap<K, T>>(Box::new(|items: Vec<(K, AnyFactory)>| {
+            let factories = unwrap_keyed_children(FactoryType::try_of::<T, E>(), items, |i| i.as_try_factory_of::<T, E>())?;
+            Ok(AnyFactory::from(TryFactory::<HashMap<K, T>, E>::new(
+                Box::new(TryMapAG::<K, T, E, HashMap<K, T>> {
+                    factories,
+                    _marker: PhantomData,
+                })
+            )))
+        }))
+    }
+
+    /// Create new aggregate instance that produces `SendFactory` for
+    /// specified type.
+    ///
+    /// Child factories should also be `SendFactory` instances.
+    pub fn new_send<T: 'static>() -> Aggregate<'a> {
+        Aggregate {
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<Vec<T>>(),
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::send_of::<T>(), index, i, |i| i.as_send_factory_of::<T>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(SendFactory::<Vec<T>>::new(
+                    Box::new(SendAG::<T> {
+                        factories,
+                    })
+                )))
+            }),
+        }
+    }
+
+    /// Create new aggregate instance that produces `SendFactory` for
+    /// specified type, that collects values into container `C`.
+    ///
+    /// Child factories should also be `SendFactory` instances.
+    pub fn new_send_collect<T: 'static, C>() -> Aggregate<'a>
+        where C: 'static + FromIterator<T>
+    {
+        Aggregate {
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<C>(),
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::send_of::<T>(), index, i, |i| i.as_send_factory_of::<T>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(SendFactory::<C>::new(
+                    Box::new(SendCollectAG::<T, C> {
+                        factories,
+                        _marker: PhantomData,
+                    })
+                )))
+            }),
+        }
+    }
+
+    /// Create new aggregate instance that produces `TryFactory` for
+    /// specified type.
+    ///
+    /// Child factories can be anything `as_try_factory_of` accepts. The
+    /// first failed child stops the construction.
+    pub fn new_try<T: 'static, E: 'static>() -> Aggregate<'a> {
+        Aggregate {
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<Vec<T>>(),
+            do_new: Box::new(|items: Vec<AnyFactory>| {
+                let factories = items.into_iter()
+                    .enumerate()
+                    .map(|(index, i)| unwrap_child(FactoryType::try_of::<T, E>(), index, i, |i| i.as_try_factory_of::<T, E>()))
+                    .collect::<Result<_, _>>()?;
+                Ok(AnyFactory::from(TryFactory::<Vec<T>, E>::new(
+                    Box::new(TryAG::<T, E> {
+                        factories,
+                    })
+                )))
+            }),
         }
     }
 
     /// Return aggregated type.
     pub fn get_arg_type(&self) -> TypeDef {
-        self.typedef.clone()
+        self.typedef
     }
 
     /// Return container type.
     pub fn get_container_type(&self) -> TypeDef {
-        self.container_typedef.clone()
+        self.container_typedef
     }
 
     /// Produces factory usable as argument for other factories.
     ///
     /// If inner factories make `int` values, this method will make factory
     /// that makes `Vec<int>` values.
-    pub fn new_factory(&self, items: Vec<Box<Any>>) -> Box<Any> {
-        (self.do_new).call((items,))
+    ///
+    /// Returns `ChildTypeMismatch` error for the first child that does not
+    /// produce the aggregated type, and `ChildKindMismatch` error for the
+    /// first child of another factory kind.
+    pub fn new_factory(&self, items: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        (self.do_new)(items)
+    }
+
+    /// Produces factory that returns values of children sorted by
+    /// priorities and constraints.
+    ///
+    /// Returns `DependencyCycle` error if constraints contradict each
+    /// other. Indexes in `ChildTypeMismatch` and `ChildKindMismatch` errors refer to the order of
+    /// specified children.
+    pub fn new_ordered_factory(&self, children: Vec<OrderedChild>) -> Result<AnyFactory, FactoryErrorKind> {
+        let order = ordered::sort_children(&children)?;
+        self.new_factory(ordered::into_factories(children, &order))
+            .map_err(|e| match e {
+                FactoryErrorKind::ChildTypeMismatch(mut e) => {
+                    e.child_index = order[e.child_index];
+                    FactoryErrorKind::ChildTypeMismatch(e)
+                },
+                FactoryErrorKind::ChildKindMismatch(mut e) => {
+                    e.child_index = order[e.child_index];
+                    FactoryErrorKind::ChildKindMismatch(e)
+                },
+                other => other,
+            })
     }
 }
 
+/// Proxy for initializing keyed aggregate factory without caring about
+/// the value type.
+///
+/// It works like `Aggregate`, but every child factory is given together
+/// with its key, and the produced factory returns a map from keys to
+/// values. If the same key is given more than once, the last child wins,
+/// and the children it shadows are never built.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use metafactory::{ argless_as_factory, cloneable, AsFactoryExt };
+/// use metafactory::aggregate::Aggregate;
+///
+/// fn main() {
+///     let aggregate = Aggregate::new_map::<&'static str, i32>();
+///
+///     let plugins = aggregate
+///         .new_factory(vec![
+///             ("one", argless_as_factory(cloneable(1i32))),
+///             ("two", argless_as_factory(|| 2i32)),
+///         ])
+///         .ok().unwrap()
+///         .as_factory_of::<HashMap<&'static str, i32>>().unwrap()
+///         .take();
+///
+///     assert_eq!(plugins["two"], 2);
+/// }
+/// ```
+pub struct MapAggregate<'a, K> {
+    key_typedef: TypeDef,
+    typedef: TypeDef,
+    container_typedef: TypeDef,
+    do_new: NewMapFactoryFn<'a, K>,
+}
+
+impl<'a, K: 'static> MapAggregate<'a, K> {
+    fn new<T: 'static, M: 'static>(do_new: NewMapFactoryFn<'a, K>) -> MapAggregate<'a, K> {
+        MapAggregate {
+            key_typedef: TypeDef::of::<K>(),
+            typedef: TypeDef::of::<T>(),
+            container_typedef: TypeDef::of::<M>(),
+            do_new,
+        }
+    }
+
+    /// Return key type.
+    pub fn get_key_type(&self) -> TypeDef {
+        self.key_typedef
+    }
+
+    /// Return aggregated type.
+    pub fn get_arg_type(&self) -> TypeDef {
+        self.typedef
+    }
+
+    /// Return container type.
+    pub fn get_container_type(&self) -> TypeDef {
+        self.container_typedef
+    }
+
+    /// Produces factory usable as argument for other factories.
+    ///
+    /// If inner factories make `int` values, this method will make factory
+    /// that makes a map of `int` values by key.
+    ///
+    /// Returns `ChildTypeMismatch` error for the first child that does not
+    /// produce the aggregated type, and `ChildKindMismatch` error for the
+    /// first child of another factory kind.
+    pub fn new_factory(&self, items: Vec<(K, AnyFactory)>) -> Result<AnyFactory, FactoryErrorKind> {
+        (self.do_new)(items)
+    }
+}
+
+/// Create a function that makes `Factory<M>` from keyed children.
+fn new_map_fn<'a, K, T, M>() -> NewMapFactoryFn<'a, K>
+    where K: 'static + Clone + PartialEq, T: 'static, M: 'static + FromIterator<(K, T)>
+{
+    Box::new(|items: Vec<(K, AnyFactory)>| {
+        let factories = unwrap_keyed_children(FactoryType::of::<T>(), items, |i| i.as_factory_of::<T>())?;
+        Ok(AnyFactory::from(Factory::<M>::new(
+            Box::new(MapAG::<K, T, M> {
+                factories,
+                _marker: PhantomData,
+            })
+        )))
+    })
+}
+
+/// Downcast all keyed children with `unwrap`, and drop the ones shadowed
+/// by a later child with the same key.
+fn unwrap_keyed_children<K, U, F>(expected: FactoryType, items: Vec<(K, AnyFactory)>, unwrap: F) -> Result<Vec<(K, U)>, FactoryErrorKind>
+    where K: PartialEq, F: Fn(AnyFactory) -> Option<U>
+{
+    let children = items.into_iter()
+        .enumerate()
+        .map(|(index, (k, i))| unwrap_child(expected, index, i, &unwrap).map(|f| (k, f)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut kept: Vec<(K, U)> = Vec::with_capacity(children.len());
+    for (key, factory) in children.into_iter().rev() {
+        if !kept.iter().any(|(k, _)| *k == key) {
+            kept.push((key, factory));
+        }
+    }
+    kept.reverse();
+    Ok(kept)
+}
+
+/// Downcast aggregate child with `unwrap`, or return `ChildKindMismatch`
+/// error if it produces expected type with factory of another kind, and
+/// `ChildTypeMismatch` error if it does not produce expected type.
+fn unwrap_child<U, F>(expected: FactoryType, index: usize, child: AnyFactory, unwrap: F) -> Result<U, FactoryErrorKind>
+    where F: FnOnce(AnyFactory) -> Option<U>
+{
+    let actual = child.get_factory_type();
+    unwrap(child).ok_or_else(|| if actual.produced_type == expected.produced_type {
+        FactoryErrorKind::ChildKindMismatch(ChildKindMismatch {
+            produced_type_name: expected.type_name,
+            ..ChildKindMismatch::new(expected.produced_type, index, expected.kind, actual.kind)
+        })
+    } else {
+        FactoryErrorKind::ChildTypeMismatch(ChildTypeMismatch {
+            expected_type_name: expected.type_name,
+            actual_type_name: actual.type_name,
+            ..ChildTypeMismatch::new(expected.produced_type, index, actual.produced_type)
+        })
+    })
+}
+
 struct AG<T: 'static> {
     factories: Vec<Factory<T>>,
 }
 
-impl<T> Clone for AG<T> {
+impl<T: 'static> Clone for AG<T> {
     fn clone(&self) -> AG<T> {
         AG::<T> {
             factories: self.factories.clone()
@@ -151,15 +563,15 @@ impl<T> Clone for AG<T> {
     }
 }
 
-impl<T> AG<T> {
+impl<T: 'static> AG<T> {
     pub fn new(factories: Vec<Factory<T>>) -> AG<T> {
         AG::<T> {
-            factories: factories
+            factories
         }
     }
 }
 
-impl<T> Getter<Vec<T>> for AG<T> {
+impl<T: 'static> Getter<Vec<T>> for AG<T> {
     fn take(&self) -> Vec<T> {
 
         // Reserve exact result size.
@@ -174,40 +586,453 @@ impl<T> Getter<Vec<T>> for AG<T> {
         items
     }
 
-    fn boxed_clone(&self) -> Box<Getter<Vec<T>> + 'static> {
-        box self.clone()
+    fn boxed_clone(&self) -> Box<dyn Getter<Vec<T>>> {
+        Box::new(self.clone())
+    }
+}
+
+struct CollectAG<T: 'static, C> {
+    factories: Vec<Factory<T>>,
+    _marker: PhantomData<fn() -> C>,
+}
+
+impl<T: 'static, C> Getter<C> for CollectAG<T, C>
+    where C: 'static + FromIterator<T>
+{
+    fn take(&self) -> C {
+        self.factories.iter()
+            .map(|f| f.take())
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<C>> {
+        Box::new(CollectAG::<T, C> {
+            factories: self.factories.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Iterator that takes values from aggregated factories one by one.
+pub struct AggregateIter<T: 'static> {
+    factories: Rc<Vec<Factory<T>>>,
+    next: usize,
+}
+
+impl<T: 'static> Iterator for AggregateIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let value = self.factories.get(self.next).map(|f| f.take());
+        if value.is_some() {
+            self.next += 1;
+        }
+        value
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.factories.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: 'static> ExactSizeIterator for AggregateIter<T> {}
+
+struct IterAG<T: 'static> {
+    factories: Rc<Vec<Factory<T>>>,
+}
+
+impl<T: 'static> Getter<AggregateIter<T>> for IterAG<T> {
+    fn take(&self) -> AggregateIter<T> {
+        AggregateIter {
+            factories: self.factories.clone(),
+            next: 0,
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<AggregateIter<T>>> {
+        Box::new(IterAG::<T> {
+            factories: Rc::new((*self.factories).clone()),
+        })
+    }
+}
+
+struct SendAG<T: 'static> {
+    factories: Vec<SendFactory<T>>,
+}
+
+impl<T: 'static> SendGetter<Vec<T>> for SendAG<T> {
+    fn take(&self) -> Vec<T> {
+        self.factories.iter()
+            .map(|f| f.take())
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<Vec<T>>> {
+        Box::new(SendAG::<T> {
+            factories: self.factories.clone()
+        })
+    }
+}
+
+struct SendCollectAG<T: 'static, C> {
+    factories: Vec<SendFactory<T>>,
+    _marker: PhantomData<fn() -> C>,
+}
+
+impl<T: 'static, C> SendGetter<C> for SendCollectAG<T, C>
+    where C: 'static + FromIterator<T>
+{
+    fn take(&self) -> C {
+        self.factories.iter()
+            .map(|f| f.take())
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<C>> {
+        Box::new(SendCollectAG::<T, C> {
+            factories: self.factories.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+struct TryAG<T: 'static, E: 'static> {
+    factories: Vec<TryFactory<T, E>>,
+}
+
+impl<T: 'static, E: 'static> TryGetter<Vec<T>, E> for TryAG<T, E> {
+    fn try_take(&self) -> Result<Vec<T>, TryTakeError<E>> {
+        self.factories.iter()
+            .enumerate()
+            .map(|(index, f)| f.try_take().map_err(|e| e.within(index)))
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TryGetter<Vec<T>, E>> {
+        Box::new(TryAG::<T, E> {
+            factories: self.factories.clone()
+        })
+    }
+}
+
+struct MapAG<K: 'static, T: 'static, M> {
+    factories: Vec<(K, Factory<T>)>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<K, T, M> Getter<M> for MapAG<K, T, M>
+    where K: 'static + Clone, T: 'static, M: 'static + FromIterator<(K, T)>
+{
+    fn take(&self) -> M {
+        self.factories.iter()
+            .map(|(k, f)| (k.clone(), f.take()))
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<M>> {
+        Box::new(MapAG::<K, T, M> {
+            factories: self.factories.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+struct SendMapAG<K: 'static, T: 'static, M> {
+    factories: Vec<(K, SendFactory<T>)>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<K, T, M> SendGetter<M> for SendMapAG<K, T, M>
+    where K: 'static + Clone + Send + Sync, T: 'static, M: 'static + FromIterator<(K, T)>
+{
+    fn take(&self) -> M {
+        self.factories.iter()
+            .map(|(k, f)| (k.clone(), f.take()))
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<M>> {
+        Box::new(SendMapAG::<K, T, M> {
+            factories: self.factories.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+struct TryMapAG<K: 'static, T: 'static, E: 'static, M> {
+    factories: Vec<(K, TryFactory<T, E>)>,
+    _marker: PhantomData<fn() -> M>,
+}
+
+impl<K, T, E, M> TryGetter<M, E> for TryMapAG<K, T, E, M>
+    where K: 'static + Clone, T: 'static, E: 'static, M: 'static + FromIterator<(K, T)>
+{
+    fn try_take(&self) -> Result<M, TryTakeError<E>> {
+        self.factories.iter()
+            .enumerate()
+            .map(|(index, (k, f))| f.try_take().map(|v| (k.clone(), v)).map_err(|e| e.within(index)))
+            .collect()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TryGetter<M, E>> {
+        Box::new(TryMapAG::<K, T, E, M> {
+            factories: self.factories.clone(),
+            _marker: PhantomData,
+        })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use { argless_as_factory, metafactory, AsFactoryExt };
-    use super::{ Aggregate };
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::collections::{ HashMap, BTreeMap, HashSet, VecDeque };
+    use typedef::TypeDef;
+    use crate::{ argless_as_factory, metafactory, cloneable, FactoryKind, AsFactoryExt };
+    use crate::error::{ FactoryErrorKind, ChildTypeMismatch, ChildKindMismatch, TypeMismatch };
+    use crate::fallible::try_metafactory;
+    use super::{ Aggregate, AggregateIter };
 
     #[test]
     fn should_be_usable_as_vec_of_types() {
-        let container = Aggregate::new::<int>();
+        let container = Aggregate::new::<i32>();
 
         let parent_metafactory = metafactory(
-            |items: Vec<int>|
+            |items: Vec<i32>|
                 items.into_iter()
                     .map(|i| format!("{}", i))
                     .collect::<Vec<String>>()
-                    .connect(", ")
+                    .join(", ")
         );
 
         let parent_getter = parent_metafactory
             .new(vec![
                 container.new_factory(
                     vec![
-                        argless_as_factory(5i),
-                        argless_as_factory(13i)
+                        argless_as_factory(cloneable(5i32)),
+                        argless_as_factory(cloneable(13i32))
                     ]
-                )
+                ).ok().unwrap()
             ]).ok().unwrap()
             .as_factory_of::<String>().unwrap()
         ;
 
         assert_eq!(parent_getter.take(), "5, 13");
     }
+
+    #[test]
+    fn should_collect_values_by_key() {
+        let aggregate = Aggregate::new_map::<i32, String>();
+        assert_eq!(aggregate.get_key_type(), TypeDef::of::<i32>());
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<HashMap<i32, String>>());
+
+        let factory = aggregate
+            .new_factory(vec![
+                (1, argless_as_factory(cloneable("a".to_string()))),
+                (2, argless_as_factory(cloneable("b".to_string()))),
+                (1, argless_as_factory(cloneable("c".to_string()))),
+            ]).ok().unwrap()
+            .as_factory_of::<HashMap<i32, String>>().unwrap();
+
+        let values = factory.clone().take();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[&1], "c");
+        assert_eq!(values[&2], "b");
+    }
+
+    #[test]
+    fn should_collect_values_into_btree_map() {
+        let aggregate = Aggregate::new_btree_map::<&'static str, i32>();
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<BTreeMap<&'static str, i32>>());
+
+        let values = aggregate
+            .new_factory(vec![
+                ("b", argless_as_factory(|| 2i32)),
+                ("a", argless_as_factory(|| 1i32)),
+            ]).ok().unwrap()
+            .as_factory_of::<BTreeMap<&'static str, i32>>().unwrap()
+            .take();
+
+        assert_eq!(values.into_iter().collect::<Vec<_>>(), vec![("a", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn metafactory_should_create_map_aggregate() {
+        let meta = metafactory(|| 5i32);
+        let aggregate = meta.new_map_aggregate::<u8, i32>().ok().unwrap();
+        assert_eq!(aggregate.get_key_type(), TypeDef::of::<u8>());
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<HashMap<u8, i32>>());
+
+        let values = aggregate
+            .new_factory(vec![(5, meta.new(Vec::new()).ok().unwrap())]).ok().unwrap()
+            .as_factory_of::<HashMap<u8, i32>>().unwrap()
+            .take();
+
+        assert_eq!(values[&5], 5);
+        assert_eq!(
+            meta.new_map_aggregate::<String, bool>().err().unwrap(),
+            FactoryErrorKind::TypeMismatch(TypeMismatch::new(TypeDef::of::<bool>(), TypeDef::of::<i32>()))
+        );
+    }
+
+    #[test]
+    fn keyed_aggregate_should_not_build_shadowed_children() {
+        let values = Aggregate::new_btree_map::<&'static str, i32>()
+            .new_factory(vec![
+                ("a", argless_as_factory(|| -> i32 { panic!("shadowed child should not be built") })),
+                ("b", argless_as_factory(cloneable(2i32))),
+                ("a", argless_as_factory(cloneable(1i32))),
+            ]).ok().unwrap()
+            .as_factory_of::<BTreeMap<&'static str, i32>>().unwrap()
+            .take();
+
+        assert_eq!(values.into_iter().collect::<Vec<_>>(), vec![("a", 1), ("b", 2)]);
+    }
+
+    #[test]
+    fn should_return_error_for_mistyped_child() {
+        let aggregate = Aggregate::new::<i32>();
+
+        let error = aggregate.new_factory(vec![
+            argless_as_factory(cloneable(1i32)),
+            argless_as_factory(cloneable(true)),
+        ]).err().unwrap();
+
+        assert_eq!(
+            error,
+            FactoryErrorKind::ChildTypeMismatch(
+                ChildTypeMismatch::new(TypeDef::of::<i32>(), 1, TypeDef::of::<bool>())
+            )
+        );
+        assert_eq!(error.to_string(), "aggregate child 1 expected i32, got bool");
+    }
+
+    #[test]
+    fn keyed_aggregate_should_return_error_for_mistyped_child() {
+        let aggregate = Aggregate::new_map::<&'static str, i32>();
+
+        assert_eq!(
+            aggregate.new_factory(vec![("a", argless_as_factory(cloneable("nope")))]).err().unwrap(),
+            FactoryErrorKind::ChildTypeMismatch(
+                ChildTypeMismatch::new(TypeDef::of::<i32>(), 0, TypeDef::of::<&'static str>())
+            )
+        );
+    }
+
+    #[test]
+    fn iter_aggregate_should_build_values_on_demand() {
+        let built = Rc::new(Cell::new(0));
+        let counting = |value: i32| {
+            let built = built.clone();
+            argless_as_factory(move || { built.set(built.get() + 1); value })
+        };
+
+        let aggregate = Aggregate::new_iter::<i32>();
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<AggregateIter<i32>>());
+
+        let factory = aggregate
+            .new_factory(vec![counting(1), counting(2), counting(3)]).ok().unwrap()
+            .as_factory_of::<AggregateIter<i32>>().unwrap();
+
+        let mut values = factory.take();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values.next(), Some(1));
+        assert_eq!(built.get(), 1);
+
+        assert_eq!(factory.clone().take().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(built.get(), 4);
+    }
+
+    #[test]
+    fn iter_aggregate_should_be_usable_as_argument() {
+        let first_even = metafactory(|mut values: AggregateIter<i32>| values.find(|v| v % 2 == 0))
+            .new(vec![
+                Aggregate::new_iter::<i32>()
+                    .new_factory(vec![
+                        argless_as_factory(cloneable(1i32)),
+                        argless_as_factory(cloneable(4i32)),
+                        argless_as_factory(|| -> i32 { panic!("should not be built") }),
+                    ]).ok().unwrap()
+            ]).ok().unwrap()
+            .as_factory_of::<Option<i32>>().unwrap();
+
+        assert_eq!(first_even.take(), Some(4));
+    }
+
+    #[test]
+    fn should_collect_values_into_container() {
+        let aggregate = Aggregate::new_collect::<i32, HashSet<i32>>();
+        assert_eq!(aggregate.get_arg_type(), TypeDef::of::<i32>());
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<HashSet<i32>>());
+
+        let values = aggregate
+            .new_factory(vec![
+                argless_as_factory(cloneable(1i32)),
+                argless_as_factory(cloneable(2i32)),
+                argless_as_factory(cloneable(1i32)),
+            ]).ok().unwrap()
+            .as_factory_of::<HashSet<i32>>().unwrap()
+            .clone()
+            .take();
+
+        assert_eq!(values.len(), 2);
+        assert!(values.contains(&2));
+    }
+
+    #[test]
+    fn should_collect_values_into_boxed_slice() {
+        let values = Aggregate::new_collect::<i32, Box<[i32]>>()
+            .new_factory(vec![argless_as_factory(cloneable(7i32)), argless_as_factory(cloneable(8i32))]).ok().unwrap()
+            .as_factory_of::<Box<[i32]>>().unwrap()
+            .take();
+
+        assert_eq!(&*values, &[7, 8]);
+    }
+
+    #[test]
+    fn metafactory_should_create_aggregate_for_container() {
+        let meta = metafactory(|| 3i32);
+
+        let aggregate = meta.new_aggregate_of::<VecDeque<i32>>().ok().unwrap();
+        assert_eq!(aggregate.get_container_type(), TypeDef::of::<VecDeque<i32>>());
+
+        let values = aggregate
+            .new_factory(vec![meta.new(Vec::new()).ok().unwrap()]).ok().unwrap()
+            .as_factory_of::<VecDeque<i32>>().unwrap()
+            .take();
+        assert_eq!(values, VecDeque::from(vec![3]));
+
+        assert_eq!(
+            meta.new_aggregate_of::<HashSet<bool>>().err().unwrap(),
+            FactoryErrorKind::TypeMismatch(TypeMismatch::new(TypeDef::of::<bool>(), TypeDef::of::<i32>()))
+        );
+    }
+
+    #[test]
+    fn container_aggregate_should_reject_fallible_children() {
+        let meta = try_metafactory(|| -> Result<i32, ()> { Ok(1) });
+
+        let error = meta.new_aggregate_of::<VecDeque<i32>>().ok().unwrap()
+            .new_factory(vec![meta.new(Vec::new()).ok().unwrap()]).err().unwrap();
+
+        assert_eq!(
+            error,
+            FactoryErrorKind::ChildKindMismatch(ChildKindMismatch::new(
+                TypeDef::of::<i32>(), 0, FactoryKind::Plain, FactoryKind::fallible::<()>()
+            ))
+        );
+    }
+
+    #[test]
+    fn send_aggregate_should_reject_plain_children() {
+        let aggregate = Aggregate::new_send_collect::<i32, Vec<i32>>();
+
+        assert_eq!(
+            aggregate.new_factory(vec![argless_as_factory(cloneable(1i32))]).err().unwrap(),
+            FactoryErrorKind::ChildKindMismatch(
+                ChildKindMismatch::new(TypeDef::of::<i32>(), 0, FactoryKind::Send, FactoryKind::Plain)
+            )
+        );
+    }
 }
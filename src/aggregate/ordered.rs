@@ -0,0 +1,218 @@
+//! Ordering of aggregate children by priority and constraints.
+//!
+//! Children with higher priority come first. A child can also require to
+//! come before or after named siblings, and these constraints win over
+//! priorities. Constraints that name missing siblings are ignored, so
+//! optional children can be left out.
+//!
+//! Children with the same priority are ordered by name, and unnamed
+//! children keep their registration order after the named ones. This makes
+//! the order the same no matter in which order children were registered.
+
+use std::cmp::Reverse;
+
+use crate::AnyFactory;
+use crate::error::{ FactoryErrorKind, DependencyCycle };
+
+/// Aggregate child factory with its position constraints.
+pub struct OrderedChild {
+    factory: AnyFactory,
+    name: Option<String>,
+    priority: i32,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl OrderedChild {
+    /// Create unnamed child with zero priority.
+    pub fn new(factory: AnyFactory) -> OrderedChild {
+        OrderedChild {
+            factory,
+            name: None,
+            priority: 0,
+            before: Vec::new(),
+            after: Vec::new(),
+        }
+    }
+
+    /// Name this child, so that siblings can refer to it.
+    pub fn named(mut self, name: &str) -> OrderedChild {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set priority. Children with higher priority come first.
+    pub fn priority(mut self, priority: i32) -> OrderedChild {
+        self.priority = priority;
+        self
+    }
+
+    /// Require this child to come before sibling with specified name.
+    pub fn before(mut self, name: &str) -> OrderedChild {
+        self.before.push(name.to_string());
+        self
+    }
+
+    /// Require this child to come after sibling with specified name.
+    pub fn after(mut self, name: &str) -> OrderedChild {
+        self.after.push(name.to_string());
+        self
+    }
+}
+
+/// Return registration indexes of children in sorted order.
+///
+/// Returns `DependencyCycle` error if constraints contradict each other.
+pub fn sort_children(children: &[OrderedChild]) -> Result<Vec<usize>, FactoryErrorKind> {
+    // Children that must come before each child.
+    let mut preceding: Vec<Vec<usize>> = vec![Vec::new(); children.len()];
+    for (index, child) in children.iter().enumerate() {
+        for name in &child.before {
+            for other in indexes_named(children, name) {
+                preceding[other].push(index);
+            }
+        }
+        for name in &child.after {
+            preceding[index].extend(indexes_named(children, name));
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(children.len());
+    let mut placed = vec![false; children.len()];
+
+    while sorted.len() < children.len() {
+        let next = (0..children.len())
+            .filter(|&index| !placed[index] && preceding[index].iter().all(|&p| placed[p]))
+            .min_by_key(|&index| {
+                let child = &children[index];
+                (Reverse(child.priority), child.name.is_none(), child.name.clone(), index)
+            });
+
+        match next {
+            Some(index) => {
+                placed[index] = true;
+                sorted.push(index);
+            },
+            None => return Err(FactoryErrorKind::DependencyCycle(
+                DependencyCycle::new(find_cycle(children, &preceding, &placed))
+            )),
+        }
+    }
+
+    Ok(sorted)
+}
+
+/// Return indexes of children with specified name.
+fn indexes_named(children: &[OrderedChild], name: &str) -> Vec<usize> {
+    children.iter()
+        .enumerate()
+        .filter(|&(_, c)| c.name.as_ref().map(|n| n == name).unwrap_or(false))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Find names of children in a cycle among children that were not placed.
+///
+/// Every such child waits for another one that was not placed, so
+/// following them must eventually come back to a visited child.
+fn find_cycle(children: &[OrderedChild], preceding: &[Vec<usize>], placed: &[bool]) -> Vec<String> {
+    let mut path: Vec<usize> = Vec::new();
+    let mut current = (0..children.len()).find(|&index| !placed[index]).unwrap();
+
+    while !path.contains(&current) {
+        path.push(current);
+        current = *preceding[current].iter().find(|&&p| !placed[p]).unwrap();
+    }
+
+    let start = path.iter().position(|&index| index == current).unwrap();
+    path[start..].iter()
+        .chain(Some(&current))
+        .map(|&index| children[index].name.clone().unwrap_or_else(|| index.to_string()))
+        .collect()
+}
+
+/// Take factories out of children in sorted order.
+pub fn into_factories(children: Vec<OrderedChild>, order: &[usize]) -> Vec<AnyFactory> {
+    let mut factories: Vec<Option<AnyFactory>> = children.into_iter().map(|c| Some(c.factory)).collect();
+    order.iter()
+        .map(|&index| factories[index].take().unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{ argless_as_factory, cloneable, AsFactoryExt };
+    use crate::error::{ FactoryErrorKind, DependencyCycle };
+    use crate::aggregate::Aggregate;
+    use super::OrderedChild;
+
+    fn child(value: &'static str) -> OrderedChild {
+        OrderedChild::new(argless_as_factory(cloneable(value)))
+    }
+
+    fn take(children: Vec<OrderedChild>) -> Vec<&'static str> {
+        Aggregate::new::<&'static str>()
+            .new_ordered_factory(children).ok().unwrap()
+            .as_factory_of::<Vec<&'static str>>().unwrap()
+            .take()
+    }
+
+    #[test]
+    fn should_order_by_priority() {
+        assert_eq!(
+            take(vec![child("low").priority(-1), child("default"), child("high").priority(5)]),
+            vec!["high", "default", "low"]
+        );
+    }
+
+    #[test]
+    fn should_not_depend_on_registration_order() {
+        let make = || vec![
+            child("b").named("b"),
+            child("a").named("a"),
+            child("c").named("c").priority(1),
+        ];
+        let mut reversed = make();
+        reversed.reverse();
+
+        assert_eq!(take(make()), vec!["c", "a", "b"]);
+        assert_eq!(take(reversed), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn constraints_should_win_over_priority() {
+        assert_eq!(
+            take(vec![
+                child("auth").named("auth").priority(10).after("log"),
+                child("log").named("log"),
+                child("handler").named("handler").priority(20).after("auth"),
+                child("timer").before("log"),
+            ]),
+            vec!["timer", "log", "auth", "handler"]
+        );
+    }
+
+    #[test]
+    fn should_ignore_constraints_on_missing_siblings() {
+        assert_eq!(
+            take(vec![child("a").after("missing"), child("b").before("missing").priority(1)]),
+            vec!["b", "a"]
+        );
+    }
+
+    #[test]
+    fn should_return_error_for_conflicting_constraints() {
+        let error = Aggregate::new::<&'static str>()
+            .new_ordered_factory(vec![
+                child("a").named("a").after("b"),
+                child("b").named("b").after("a"),
+            ]).err().unwrap();
+
+        assert_eq!(
+            error,
+            FactoryErrorKind::DependencyCycle(DependencyCycle::new(
+                vec!["a".to_string(), "b".to_string(), "a".to_string()]
+            ))
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Implements argument names and descriptions for any metafactory.
+//!
+//! Metafactories created from closures only know argument types. Wrapping
+//! one in `Annotated` adds names and descriptions to `get_args`, and
+//! argument errors returned by the wrapped metafactory mention argument
+//! names instead of indexes.
+//!
+//! ```
+//! use metafactory::{ metafactory, argless_as_factory, cloneable };
+//! use metafactory::annotated::Annotated;
+//!
+//! fn main() {
+//!     let meta_area = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+//!         .arg_names(&["width", "height"]).ok().unwrap()
+//!         .arg_description("height", "Height in pixels").ok().unwrap()
+//!         .build();
+//!
+//!     let args = meta_area.get_args();
+//!     assert_eq!(args[1].name, Some("height".to_string()));
+//!     assert_eq!(args[1].description, Some("Height in pixels".to_string()));
+//!
+//!     let error = meta_area.new(vec![
+//!         argless_as_factory(cloneable(2i32)),
+//!         argless_as_factory(cloneable(true)),
+//!     ]).err().unwrap();
+//!
+//!     assert_eq!(error.to_string(), "argument \"height\" expected i32, got bool");
+//! }
+//! ```
+
+use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, ArgDescriptor, FactoryType };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgIndexOutOfRange, DuplicateArgument, UnknownArgument };
+use crate::aggregate::Aggregate;
+
+/// Metafactory wrapper that adds argument names and descriptions.
+pub struct Annotated {
+    metafactory: Box<dyn MetaFactory>,
+    args: Vec<ArgDescriptor>,
+}
+
+impl Annotated {
+    /// Wrap metafactory, starting with its own argument descriptors.
+    pub fn new(metafactory: Box<dyn MetaFactory>) -> Annotated {
+        let args = metafactory.get_args();
+        Annotated {
+            metafactory,
+            args,
+        }
+    }
+
+    /// Name all arguments in order.
+    ///
+    /// Returns `ArgCountMismatch` error if there is not exactly one name
+    /// for every argument, and `DuplicateArgument` error if a name is
+    /// listed more than once.
+    pub fn arg_names(mut self, names: &[&str]) -> Result<Annotated, FactoryErrorKind> {
+        if names.len() != self.args.len() {
+            return Err(FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(self.args.len(), names.len())));
+        }
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(FactoryErrorKind::DuplicateArgument(DuplicateArgument::new(name)));
+            }
+        }
+
+        for (arg, name) in self.args.iter_mut().zip(names.iter()) {
+            arg.name = Some(name.to_string());
+        }
+        Ok(self)
+    }
+
+    /// Name argument at specified index.
+    ///
+    /// Returns `ArgIndexOutOfRange` error if there is no argument at the
+    /// index, and `DuplicateArgument` error if another argument already
+    /// has this name.
+    pub fn arg_name(mut self, index: usize, name: &str) -> Result<Annotated, FactoryErrorKind> {
+        if index >= self.args.len() {
+            return Err(FactoryErrorKind::ArgIndexOutOfRange(ArgIndexOutOfRange::new(index, self.args.len())));
+        }
+        let used = self.args.iter().enumerate()
+            .any(|(i, a)| i != index && a.name.as_ref().map(|n| n == name).unwrap_or(false));
+        if used {
+            return Err(FactoryErrorKind::DuplicateArgument(DuplicateArgument::new(name)));
+        }
+
+        self.args[index].name = Some(name.to_string());
+        Ok(self)
+    }
+
+    /// Describe the argument with specified name.
+    ///
+    /// Returns `UnknownArgument` error if there is no argument with this name.
+    pub fn arg_description(mut self, name: &str, description: &str) -> Result<Annotated, FactoryErrorKind> {
+        match self.args.iter_mut().find(|a| a.name.as_ref().map(|n| n == name).unwrap_or(false)) {
+            Some(arg) => arg.description = Some(description.to_string()),
+            None => return Err(FactoryErrorKind::UnknownArgument(UnknownArgument::new(name))),
+        }
+        Ok(self)
+    }
+
+    /// Finish annotating.
+    pub fn build(self) -> Box<dyn MetaFactory> {
+        Box::new(self)
+    }
+
+    fn name_errors(&self, error: FactoryErrorKind) -> FactoryErrorKind {
+        error.with_arg_names(&|index| self.args.get(index).and_then(|a| a.name.clone()))
+    }
+}
+
+impl MetaFactory for Annotated {
+    fn get_type(&self) -> TypeDef {
+        self.metafactory.get_type()
+    }
+
+    fn get_arg_types(&self) -> Vec<TypeDef> {
+        self.metafactory.get_arg_types()
+    }
+
+    fn get_factory_type(&self) -> FactoryType {
+        self.metafactory.get_factory_type()
+    }
+
+    fn get_args(&self) -> Vec<ArgDescriptor> {
+        self.args.clone()
+    }
+
+    fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        self.metafactory.new(arg_getters).map_err(|e| self.name_errors(e))
+    }
+
+    fn new_aggregate(&self) -> Aggregate<'static> {
+        self.metafactory.new_aggregate()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use typedef::TypeDef;
+    use crate::{ metafactory, argless_as_factory, cloneable, ArgDescriptor, AnyFactory, FactoryType, AsFactoryExt };
+    use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgIndexOutOfRange, DuplicateArgument, MissingArgument, UnknownArgument };
+    use crate::lazy::{ lazy_metafactory, Lazy };
+    use super::Annotated;
+
+    #[test]
+    fn should_describe_named_arguments() {
+        let meta = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+            .arg_name(1, "height").ok().unwrap()
+            .arg_description("height", "Height in pixels").ok().unwrap()
+            .build();
+
+        assert_eq!(meta.get_args(), vec![
+            ArgDescriptor::required(TypeDef::of::<i32>()),
+            ArgDescriptor::required(TypeDef::of::<i32>())
+                .with_name("height")
+                .with_description("Height in pixels"),
+        ]);
+        assert_eq!(meta.get_arg_types(), vec![TypeDef::of::<i32>(), TypeDef::of::<i32>()]);
+    }
+
+    #[test]
+    fn should_keep_optional_flag() {
+        let meta = Annotated::new(lazy_metafactory(|a: Lazy<i32>, b: Option<i32>| *a.get() + b.unwrap_or(0)))
+            .arg_names(&["base", "extra"]).ok().unwrap()
+            .build();
+
+        assert!(!meta.get_args()[0].optional);
+        assert!(meta.get_args()[1].optional);
+    }
+
+    #[test]
+    fn should_name_arguments_in_all_errors() {
+        let meta = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+            .arg_names(&["width", "height"]).ok().unwrap()
+            .build();
+
+        match meta.validate(&[FactoryType::of::<bool>(), FactoryType::of::<bool>()]) {
+            Err(FactoryErrorKind::Multiple(errors)) => {
+                let names: Vec<_> = errors.iter().map(|e| match *e {
+                    FactoryErrorKind::ArgTypeMismatch(ref e) => e.argument_name.clone().unwrap(),
+                    _ => panic!("Expected ArgTypeMismatch error!"),
+                }).collect();
+                assert_eq!(names, vec!["width", "height"]);
+            },
+            _ => panic!("Expected Multiple error!"),
+        }
+
+        assert_eq!(
+            meta.new(vec![argless_as_factory(cloneable(1i32)), AnyFactory::empty()]).err().unwrap(),
+            FactoryErrorKind::MissingArgument(MissingArgument {
+                argument_name: Some("height".to_string()),
+                ..MissingArgument::new(TypeDef::of::<i32>(), 1)
+            })
+        );
+    }
+
+    #[test]
+    fn should_bind_arguments_by_name() {
+        let meta = Annotated::new(lazy_metafactory(|a: Lazy<i32>, b: Option<i32>| *a.get() - b.unwrap_or(0)))
+            .arg_names(&["base", "extra"]).ok().unwrap()
+            .build();
+
+        let mut args = HashMap::new();
+        args.insert("base".to_string(), argless_as_factory(cloneable(5i32)));
+        let factory = meta.new_named(args).ok().unwrap().as_factory_of::<i32>().unwrap();
+        assert_eq!(factory.take(), 5);
+
+        let mut args = HashMap::new();
+        args.insert("extra".to_string(), argless_as_factory(cloneable(2i32)));
+        args.insert("base".to_string(), argless_as_factory(cloneable(5i32)));
+        let factory = meta.new_named(args).ok().unwrap().as_factory_of::<i32>().unwrap();
+        assert_eq!(factory.take(), 3);
+    }
+
+    #[test]
+    fn should_return_unknown_and_missing_named_arguments() {
+        let meta = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+            .arg_names(&["width", "height"]).ok().unwrap()
+            .build();
+
+        let mut args = HashMap::new();
+        args.insert("width".to_string(), argless_as_factory(cloneable(1i32)));
+        args.insert("depth".to_string(), argless_as_factory(cloneable(1i32)));
+        assert_eq!(
+            meta.new_named(args).err().unwrap(),
+            FactoryErrorKind::UnknownArgument(UnknownArgument::new("depth"))
+        );
+
+        let mut args = HashMap::new();
+        args.insert("width".to_string(), argless_as_factory(cloneable(1i32)));
+        assert_eq!(
+            meta.new_named(args).err().unwrap().to_string(),
+            "argument \"height\" expected i32, got nothing"
+        );
+    }
+
+    #[test]
+    fn should_return_unknown_argument_when_annotating_missing_argument() {
+        let annotated = Annotated::new(metafactory(|w: i32| w)).arg_names(&["width"]).ok().unwrap();
+
+        assert_eq!(
+            annotated.arg_description("nope", "Nothing").err().unwrap(),
+            FactoryErrorKind::UnknownArgument(UnknownArgument::new("nope"))
+        );
+    }
+
+    #[test]
+    fn should_reject_names_that_do_not_match_arguments() {
+        let area = || Annotated::new(metafactory(|w: i32, h: i32| w * h));
+
+        assert_eq!(
+            area().arg_names(&["width", "height", "depth"]).err().unwrap(),
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 3))
+        );
+        assert_eq!(
+            area().arg_names(&["width"]).err().unwrap(),
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 1))
+        );
+        assert_eq!(
+            area().arg_names(&["width", "width"]).err().unwrap(),
+            FactoryErrorKind::DuplicateArgument(DuplicateArgument::new("width"))
+        );
+    }
+
+    #[test]
+    fn should_reject_name_of_another_argument() {
+        let annotated = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+            .arg_names(&["width", "height"]).ok().unwrap();
+
+        assert_eq!(
+            annotated.arg_name(1, "width").err().unwrap(),
+            FactoryErrorKind::DuplicateArgument(DuplicateArgument::new("width"))
+        );
+
+        let renamed = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+            .arg_names(&["width", "height"]).ok().unwrap()
+            .arg_name(1, "height").ok().unwrap()
+            .build();
+        assert_eq!(renamed.get_args()[1].name, Some("height".to_string()));
+    }
+
+    #[test]
+    fn should_return_index_error_when_naming_missing_argument() {
+        let error = Annotated::new(metafactory(|w: i32| w)).arg_name(1, "height").err().unwrap();
+
+        assert_eq!(error, FactoryErrorKind::ArgIndexOutOfRange(ArgIndexOutOfRange::new(1, 1)));
+        assert_eq!(error.to_string(), "argument index 1 is out of range for 1 arguments");
+    }
+}
@@ -0,0 +1,357 @@
+//! Implements automatic wiring of metafactories by their types.
+//!
+//! Every argument of a metafactory is plugged in with the factory of
+//! the only metafactory that produces the argument type. If the argument
+//! is a `Vec<T>`, and there is no metafactory that produces it directly,
+//! all metafactories producing `T` are collected into an aggregate.
+//! Optional arguments without a provider are left empty.
+//!
+//! ```
+//! use metafactory::{ metafactory, cloneable };
+//! use metafactory::autowire::Autowire;
+//!
+//! fn main() {
+//!     let mut autowire = Autowire::new();
+//!
+//!     autowire.add(metafactory(cloneable(2i32)));
+//!     autowire.add(metafactory(|| true));
+//!     autowire.add(metafactory(|| false));
+//!     autowire.add(metafactory(|count: i32, flags: Vec<bool>| {
+//!         format!("{} {:?}", count, flags)
+//!     }));
+//!
+//!     let text = autowire.resolve::<String>().ok().unwrap();
+//!
+//!     assert_eq!(text.take(), "2 [true, false]");
+//! }
+//! ```
+
+use std::collections::HashSet;
+
+use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, Factory, TypeName, AsFactoryExt };
+use crate::error::{ FactoryErrorKind, MissingProvider, AmbiguousProvider, DependencyCycle };
+
+/// Metafactory together with its produced and aggregate container types.
+struct Provider {
+    metafactory: Box<dyn MetaFactory>,
+    produced_type: TypeDef,
+    container_type: TypeDef,
+}
+
+/// Set of metafactories wired together by their types.
+#[derive(Default)]
+pub struct Autowire {
+    providers: Vec<Provider>,
+}
+
+impl Autowire {
+    /// Create new empty set.
+    pub fn new() -> Autowire {
+        Autowire {
+            providers: Vec::new(),
+        }
+    }
+
+    /// Add metafactory to the set.
+    pub fn add(&mut self, metafactory: Box<dyn MetaFactory>) {
+        self.providers.push(Provider {
+            produced_type: metafactory.get_type(),
+            container_type: metafactory.new_aggregate().get_container_type(),
+            metafactory,
+        });
+    }
+
+    /// Resolve factory of specified type and downcast it to `Factory<T>`.
+    ///
+    /// Returns `MissingProvider` error if the value is not produced by
+    /// a plain `Factory<T>`.
+    pub fn resolve<T: 'static>(&self) -> Result<Factory<T>, FactoryErrorKind> {
+        self.resolve_type(TypeDef::of::<T>(), TypeName::of::<T>())?
+            .as_factory_of::<T>()
+            .ok_or_else(|| FactoryErrorKind::MissingProvider(MissingProvider {
+                required_type_name: TypeName::of::<T>(),
+                ..MissingProvider::new(TypeDef::of::<T>())
+            }))
+    }
+
+    /// Resolve factory of specified type with all its dependencies.
+    ///
+    /// Dependency cycles are detected before any factory is created.
+    pub fn resolve_any(&self, required_type: TypeDef) -> Result<AnyFactory, FactoryErrorKind> {
+        self.resolve_type(required_type, TypeName::unknown())
+    }
+
+    fn resolve_type(&self, required_type: TypeDef, type_name: TypeName) -> Result<AnyFactory, FactoryErrorKind> {
+        self.check_cycles(required_type, type_name, &mut Vec::new(), &mut HashSet::new())?;
+        self.build_type(required_type, type_name)
+    }
+
+    /// Find metafactories that should be used for the required type.
+    ///
+    /// Returns either the single metafactory that produces the type, or
+    /// all the metafactories that should be aggregated into it. The type
+    /// name is only used in errors.
+    fn providers_of(&self, required_type: TypeDef, type_name: TypeName) -> Result<Vec<&dyn MetaFactory>, FactoryErrorKind> {
+        let providers = self.providers.iter()
+            .filter(|p| p.produced_type == required_type)
+            .map(|p| &*p.metafactory)
+            .collect::<Vec<_>>();
+
+        match providers.len() {
+            1 => return Ok(providers),
+            0 => (),
+            count => return Err(FactoryErrorKind::AmbiguousProvider(AmbiguousProvider {
+                required_type_name: type_name,
+                ..AmbiguousProvider::new(required_type, count)
+            })),
+        }
+
+        let items = self.providers.iter()
+            .filter(|p| p.container_type == required_type)
+            .map(|p| &*p.metafactory)
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            return Err(FactoryErrorKind::MissingProvider(MissingProvider {
+                required_type_name: type_name,
+                ..MissingProvider::new(required_type)
+            }));
+        }
+
+        Ok(items)
+    }
+
+    /// Check if any metafactory produces the type or its items.
+    fn has_provider(&self, required_type: TypeDef) -> bool {
+        self.providers.iter()
+            .any(|p| p.produced_type == required_type || p.container_type == required_type)
+    }
+
+    /// Check the required type and its dependencies for cycles.
+    ///
+    /// Types in `checked` were already checked with all their dependencies,
+    /// so shared dependencies are walked only once. The `stack` keeps type
+    /// names for the cycle path.
+    fn check_cycles(
+        &self,
+        required_type: TypeDef,
+        type_name: TypeName,
+        stack: &mut Vec<(TypeDef, TypeName)>,
+        checked: &mut HashSet<TypeDef>
+    ) -> Result<(), FactoryErrorKind> {
+        if checked.contains(&required_type) {
+            return Ok(());
+        }
+
+        if let Some(position) = stack.iter().position(|(t, _)| *t == required_type) {
+            let mut path: Vec<String> = stack[position..].iter().map(|(t, name)| name.describe(*t)).collect();
+            path.push(type_name.describe(required_type));
+            return Err(FactoryErrorKind::DependencyCycle(DependencyCycle::new(path)));
+        }
+
+        let providers = self.providers_of(required_type, type_name)?;
+
+        stack.push((required_type, type_name));
+        for metafactory in providers {
+            for arg in metafactory.get_args() {
+                if arg.optional && !self.has_provider(arg.arg_type) {
+                    continue;
+                }
+                self.check_cycles(arg.arg_type, arg.type_name, stack, checked)?;
+            }
+        }
+        stack.pop();
+        checked.insert(required_type);
+
+        Ok(())
+    }
+
+    fn build_type(&self, required_type: TypeDef, type_name: TypeName) -> Result<AnyFactory, FactoryErrorKind> {
+        let providers = self.providers_of(required_type, type_name)?;
+
+        if providers[0].get_type() == required_type {
+            return self.build(providers[0]);
+        }
+
+        let factories = providers.iter()
+            .map(|m| self.build(*m))
+            .collect::<Result<Vec<_>, _>>()?;
+        providers[0].new_aggregate().new_factory(factories)
+    }
+
+    fn build(&self, metafactory: &dyn MetaFactory) -> Result<AnyFactory, FactoryErrorKind> {
+        let arguments = metafactory.get_args().into_iter()
+            .map(|arg| if arg.optional && !self.has_provider(arg.arg_type) {
+                Ok(AnyFactory::empty())
+            } else {
+                self.build_type(arg.arg_type, arg.type_name)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        metafactory.new(arguments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use typedef::TypeDef;
+    use crate::{ metafactory, argless_as_factory, cloneable, MetaFactory, AnyFactory };
+    use crate::aggregate::Aggregate;
+    use crate::error::{ FactoryErrorKind, MissingProvider };
+    use crate::lazy::lazy_metafactory;
+    use super::Autowire;
+
+    #[test]
+    fn should_wire_arguments_by_type() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|a: i32, b: u8| a as i64 + b as i64));
+        autowire.add(metafactory(cloneable(5u8)));
+        autowire.add(metafactory(|| 10i32));
+        autowire.add(metafactory(|sum: i64| sum.to_string()));
+
+        assert_eq!(autowire.resolve::<String>().ok().unwrap().take(), "15");
+    }
+
+    #[test]
+    fn should_return_missing_provider() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|a: i32| a.to_string()));
+
+        match autowire.resolve::<String>() {
+            Err(FactoryErrorKind::MissingProvider(e)) => {
+                assert_eq!(e.required_type, TypeDef::of::<i32>());
+            },
+            _ => panic!("Expected MissingProvider error!"),
+        }
+    }
+
+    #[test]
+    fn should_return_ambiguous_provider() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|a: i32| a.to_string()));
+        autowire.add(metafactory(|| 1i32));
+        autowire.add(metafactory(|| 2i32));
+
+        match autowire.resolve::<String>() {
+            Err(FactoryErrorKind::AmbiguousProvider(e)) => {
+                assert_eq!(e.required_type, TypeDef::of::<i32>());
+                assert_eq!(e.provider_count, 2);
+            },
+            _ => panic!("Expected AmbiguousProvider error!"),
+        }
+    }
+
+    #[test]
+    fn should_aggregate_providers_for_vec_argument() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|items: Vec<i32>| items.iter().sum::<i32>() as i64));
+        autowire.add(metafactory(|| 1u8));
+        autowire.add(metafactory(|a: u8| a as i32 * 2));
+        autowire.add(metafactory(|a: u8| a as i32 * 3));
+
+        assert_eq!(autowire.resolve::<Vec<i32>>().ok().unwrap().take(), vec![2, 3]);
+        assert_eq!(autowire.resolve::<i64>().ok().unwrap().take(), 5);
+    }
+
+    #[test]
+    fn should_return_dependency_cycle() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|a: u8| a.to_string()));
+        autowire.add(metafactory(|b: u16| b as u8));
+        autowire.add(metafactory(|c: u32| c as u16));
+        autowire.add(metafactory(|a: u8| a as u32));
+
+        match autowire.resolve::<String>() {
+            Err(FactoryErrorKind::DependencyCycle(e)) => {
+                assert_eq!(e.to_string(), "dependency cycle u8 -> u16 -> u32 -> u8");
+            },
+            _ => panic!("Expected DependencyCycle error!"),
+        }
+    }
+
+    #[test]
+    fn should_return_dependency_cycle_through_aggregate() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|items: Vec<i32>| items.len() as i32));
+
+        match autowire.resolve::<Vec<i32>>() {
+            Err(FactoryErrorKind::DependencyCycle(e)) => {
+                assert_eq!(e.path, vec!["alloc::vec::Vec<i32>", "alloc::vec::Vec<i32>"]);
+            },
+            _ => panic!("Expected DependencyCycle error!"),
+        }
+    }
+
+    #[test]
+    fn should_leave_optional_argument_without_provider_empty() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(cloneable(2i32)));
+        autowire.add(lazy_metafactory(|a: Option<i32>, b: Option<u8>| format!("{:?} {:?}", a, b)));
+
+        assert_eq!(autowire.resolve::<String>().ok().unwrap().take(), "Some(2) None");
+    }
+
+    #[test]
+    fn should_return_missing_dependency_of_optional_argument_provider() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|v: u64| v as i32));
+        autowire.add(lazy_metafactory(|v: Option<i32>| format!("{:?}", v)));
+
+        match autowire.resolve::<String>() {
+            Err(FactoryErrorKind::MissingProvider(e)) => {
+                assert_eq!(e, MissingProvider::new(TypeDef::of::<u64>()));
+            },
+            _ => panic!("Expected MissingProvider error!"),
+        }
+    }
+
+    #[test]
+    fn should_prefer_direct_vec_provider() {
+        let mut autowire = Autowire::new();
+        autowire.add(metafactory(|| 1i32));
+        autowire.add(metafactory(|| vec![7i32]));
+
+        assert_eq!(autowire.resolve::<Vec<i32>>().ok().unwrap().take(), vec![7]);
+    }
+
+    #[test]
+    fn should_check_shared_dependencies_once() {
+        let calls = Rc::new(Cell::new(0));
+        let mut autowire = Autowire::new();
+        autowire.add(Box::new(CountingArgs { calls: calls.clone() }));
+        autowire.add(metafactory(|a: u8, b: u8| a as u16 + b as u16));
+        autowire.add(metafactory(|a: u16, b: u16| a as u32 + b as u32));
+        autowire.add(metafactory(|a: u32, b: u32| a as u64 + b as u64));
+
+        assert_eq!(autowire.resolve::<u64>().ok().unwrap().take(), 8);
+        // Checked once, and built for every path to it.
+        assert_eq!(calls.get(), 1 + 8);
+    }
+
+    /// Produces `1u8` and counts how many times its arguments are listed.
+    struct CountingArgs {
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl MetaFactory for CountingArgs {
+        fn get_type(&self) -> TypeDef {
+            TypeDef::of::<u8>()
+        }
+
+        fn get_arg_types(&self) -> Vec<TypeDef> {
+            self.calls.set(self.calls.get() + 1);
+            Vec::new()
+        }
+
+        fn new(&self, _arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+            Ok(argless_as_factory(cloneable(1u8)))
+        }
+
+        fn new_aggregate(&self) -> Aggregate<'static> {
+            Aggregate::new::<u8>()
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Implements metafactories for closures with any number of dependencies.
+//!
+//! Closure metafactories support up to 12 arguments. A closure converted
+//! with `dependency_metafactory` instead takes a single argument that
+//! implements `Dependencies`: a tuple, or a struct declared with the
+//! `dependencies!` macro. Every tuple element or struct field is wired
+//! as a separate argument, and `get_arg_types` lists all of them in order.
+//!
+//! Tuples of up to 12 elements are supported, and structs can have any
+//! number of fields.
+//!
+//! Structs that only pass their fields to the constructor can derive
+//! `MetaFactory` with the `metafactory-derive` crate instead. The derive
+//! implements `Dependencies` for the struct and `ToMetaFactory` for its
+//! `Constructor`, so `metafactory(constructor::<Foo>())` builds `Foo`
+//! from its fields, with field names as argument names.
+//!
+//! ```
+//! #[macro_use] extern crate metafactory;
+//!
+//! use metafactory::{ argless_as_factory, cloneable, AsFactoryExt };
+//! use metafactory::dependencies::dependency_metafactory;
+//!
+//! dependencies! {
+//!     struct Size {
+//!         width: i32,
+//!         height: i32,
+//!         unit: &'static str,
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let meta_area = dependency_metafactory(|size: Size| {
+//!         format!("{}{}", size.width * size.height, size.unit)
+//!     });
+//!
+//!     assert_eq!(meta_area.get_arg_types().len(), 3);
+//!
+//!     let area = meta_area.new(vec![
+//!         argless_as_factory(cloneable(3i32)),
+//!         argless_as_factory(cloneable(4i32)),
+//!         argless_as_factory(cloneable("px")),
+//!     ]).ok().unwrap().as_factory_of::<String>().unwrap();
+//!
+//!     assert_eq!(area.take(), "12px");
+//! }
+//! ```
+
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::vec;
+
+// Used by `dependencies!` macro in other crates.
+#[doc(hidden)]
+pub use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, ArgDescriptor, Factory, Getter, AsFactoryExt, FactoryKind, FactoryType, TypeName };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, MissingArgument };
+use crate::aggregate::Aggregate;
+
+/// Value assembled from several argument factories.
+///
+/// Implementations should list and unwrap their parts in the same order.
+pub trait Dependencies: Sized + 'static {
+    /// Return types of all parts.
+    fn arg_types() -> Vec<TypeDef>;
+
+    /// Describe all parts as arguments.
+    ///
+    /// By default all parts are required arguments of `arg_types`, without
+    /// readable type names.
+    fn args() -> Vec<ArgDescriptor> {
+        Self::arg_types().into_iter().map(ArgDescriptor::required).collect()
+    }
+
+    /// Downcast argument factories for all parts, and return a factory
+    /// that assembles the value from them.
+    fn unwrap_factories(factories: &mut FactoryList) -> Result<Factory<Self>, FactoryErrorKind>;
+}
+
+/// Argument factories, downcast while building the metafactory.
+pub struct FactoryList {
+    source: vec::IntoIter<AnyFactory>,
+    next_index: usize,
+}
+
+impl FactoryList {
+    fn new(arg_getters: Vec<AnyFactory>) -> FactoryList {
+        FactoryList {
+            source: arg_getters.into_iter(),
+            next_index: 0,
+        }
+    }
+
+    /// Downcast the next argument to `Factory<T>`.
+    ///
+    /// Returns `MissingArgument` error if there are no more arguments.
+    pub fn unwrap<T: 'static>(&mut self) -> Result<Factory<T>, FactoryErrorKind> {
+        let arg_index = self.next_index;
+        let maybe_factory = match self.source.next() {
+            Some(factory) => factory,
+            None => return Err(FactoryErrorKind::MissingArgument(MissingArgument {
+                expected_type_name: TypeName::of::<T>(),
+                ..MissingArgument::new(TypeDef::of::<T>(), arg_index)
+            })),
+        };
+        self.next_index += 1;
+        Ok(try_unwrap_factory!(T, maybe_factory, arg_index))
+    }
+}
+
+/// Create a factory that assembles values with closure.
+///
+/// The closure should take parts from factories it owns, so that cloning
+/// the closure clones the factories.
+pub fn assemble<T, F>(closure: F) -> Factory<T>
+    where T: 'static, F: Fn() -> T + Clone + 'static
+{
+    Factory::new(Box::new(Assemble { closure }))
+}
+
+/// Getter that assembles values with closure.
+struct Assemble<F> {
+    closure: F,
+}
+
+impl<T, F> Getter<T> for Assemble<F>
+    where T: 'static, F: Fn() -> T + Clone + 'static
+{
+    fn take(&self) -> T {
+        (self.closure)()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(Assemble {
+            closure: self.closure.clone(),
+        })
+    }
+}
+
+macro_rules! tuple_dependencies_impl(
+    ($($_A:ident $_a:ident),+)
+    =>
+    (
+        impl<$($_A: 'static),+> Dependencies for ($($_A,)+) {
+            fn arg_types() -> Vec<TypeDef> {
+                vec![$(TypeDef::of::<$_A>()),+]
+            }
+
+            fn args() -> Vec<ArgDescriptor> {
+                vec![$(ArgDescriptor::of::<$_A>()),+]
+            }
+
+            fn unwrap_factories(factories: &mut FactoryList) -> Result<Factory<($($_A,)+)>, FactoryErrorKind> {
+                $(
+                    let $_a = factories.unwrap::<$_A>()?;
+                )+
+                Ok(assemble(move || ($($_a.take(),)+)))
+            }
+        }
+    )
+);
+
+tuple_dependencies_impl!(A1 a1);
+tuple_dependencies_impl!(A1 a1, A2 a2);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7, A8 a8);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7, A8 a8, A9 a9);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7, A8 a8, A9 a9, A10 a10);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7, A8 a8, A9 a9, A10 a10, A11 a11);
+tuple_dependencies_impl!(A1 a1, A2 a2, A3 a3, A4 a4, A5 a5, A6 a6, A7 a7, A8 a8, A9 a9, A10 a10, A11 a11, A12 a12);
+
+/// Declare a struct and implement `Dependencies` for it.
+///
+/// Every field is wired as a separate argument, in declaration order.
+#[macro_export]
+macro_rules! dependencies(
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $Name:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident : $T:ty),+ $(,)?
+        }
+    )
+    =>
+    (
+        $(#[$attr])*
+        $vis struct $Name {
+            $($(#[$field_attr])* $field_vis $field: $T),+
+        }
+
+        impl $crate::dependencies::Dependencies for $Name {
+            fn arg_types() -> Vec<$crate::dependencies::TypeDef> {
+                vec![$($crate::dependencies::TypeDef::of::<$T>()),+]
+            }
+
+            fn args() -> Vec<$crate::ArgDescriptor> {
+                vec![$($crate::ArgDescriptor::of::<$T>()),+]
+            }
+
+            fn unwrap_factories(
+                factories: &mut $crate::dependencies::FactoryList
+            ) -> Result<$crate::Factory<$Name>, $crate::error::FactoryErrorKind> {
+                $(
+                    let $field = factories.unwrap::<$T>()?;
+                )+
+                Ok($crate::dependencies::assemble(move || $Name {
+                    $($field: $field.take()),+
+                }))
+            }
+        }
+    )
+);
+
+/// Source that builds `T` from its dependencies.
+///
+/// `ToMetaFactory` is implemented for it by `#[derive(MetaFactory)]`.
+pub struct Constructor<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Create a new `Constructor` for `T`.
+pub fn constructor<T>() -> Constructor<T> {
+    Constructor {
+        _marker: PhantomData,
+    }
+}
+
+/// Create a new `MetaFactory` from a closure that takes all its
+/// dependencies as a single `Dependencies` value.
+pub fn dependency_metafactory<D, T, F>(closure: F) -> Box<dyn MetaFactory>
+    where D: Dependencies, T: 'static, F: FnMut(D) -> T + 'static
+{
+    Box::new(ClosureScope::<F, D, T> {
+        closure: Rc::new(RefCell::new(closure)),
+        _marker: PhantomData,
+    })
+}
+
+/// Shared closure reference, used as `MetaFactory`.
+struct ClosureScope<F, D, T> {
+    closure: Rc<RefCell<F>>,
+    _marker: PhantomData<fn(D) -> T>,
+}
+
+impl<F, D, T> MetaFactory for ClosureScope<F, D, T>
+    where D: Dependencies, T: 'static, F: FnMut(D) -> T + 'static
+{
+    fn get_type(&self) -> TypeDef {
+        TypeDef::of::<T>()
+    }
+
+    fn get_arg_types(&self) -> Vec<TypeDef> {
+        D::arg_types()
+    }
+
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::of::<T>()
+    }
+
+    fn get_args(&self) -> Vec<ArgDescriptor> {
+        D::args()
+    }
+
+    fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        assert_arg_count!(D::arg_types().len(), arg_getters.len());
+
+        let dependencies = D::unwrap_factories(&mut FactoryList::new(arg_getters))?;
+
+        // Dependencies are produced before the closure is borrowed.
+        let closure = self.closure.clone();
+        Ok(AnyFactory::from(dependencies.map(move |d| (*closure.borrow_mut())(d))))
+    }
+
+    fn new_aggregate(&self) -> Aggregate<'static> {
+        Aggregate::new::<T>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use typedef::TypeDef;
+    use crate::{ argless_as_factory, cloneable, AnyFactory, Factory, FactoryType, AsFactoryExt };
+    use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch, MissingArgument };
+    use super::{ dependency_metafactory, assemble, Dependencies, FactoryList };
+
+    dependencies! {
+        /// More dependencies than a closure can take.
+        struct Many {
+            a1: i16, a2: i16, a3: i16, a4: i16, a5: i16,
+            a6: i16, a7: i16, a8: i16, a9: i16, a10: i16,
+            a11: i16, a12: i16, a13: i16, flag: bool,
+        }
+    }
+
+    /// Unwraps more factories than it lists.
+    struct Mismatched;
+
+    impl Dependencies for Mismatched {
+        fn arg_types() -> Vec<TypeDef> {
+            vec![TypeDef::of::<i32>()]
+        }
+
+        fn unwrap_factories(factories: &mut FactoryList) -> Result<Factory<Mismatched>, FactoryErrorKind> {
+            factories.unwrap::<i32>()?;
+            factories.unwrap::<bool>()?;
+            Ok(assemble(|| Mismatched))
+        }
+    }
+
+    #[test]
+    fn should_flatten_tuple_argument() {
+        let meta = dependency_metafactory(|(a, b): (i32, bool)| if b { a } else { -a });
+
+        assert_eq!(meta.get_arg_types(), vec![TypeDef::of::<i32>(), TypeDef::of::<bool>()]);
+
+        let factory = meta.new(vec![
+            argless_as_factory(cloneable(5i32)),
+            argless_as_factory(cloneable(false)),
+        ]).ok().unwrap().as_factory_of::<i32>().unwrap();
+
+        assert_eq!(factory.take(), -5);
+        assert_eq!(factory.clone().take(), -5);
+    }
+
+    #[test]
+    fn should_wire_struct_with_more_than_12_fields() {
+        let meta = dependency_metafactory(|m: Many| {
+            let sum = m.a1 + m.a2 + m.a3 + m.a4 + m.a5 + m.a6 + m.a7
+                + m.a8 + m.a9 + m.a10 + m.a11 + m.a12 + m.a13;
+            if m.flag { sum } else { 0 }
+        });
+
+        let mut args: Vec<AnyFactory> = (0..13).map(|_| argless_as_factory(cloneable(2i16))).collect();
+        args.push(argless_as_factory(cloneable(true)));
+
+        assert_eq!(meta.get_arg_types().len(), 14);
+        assert_eq!(meta.get_arg_types()[13], TypeDef::of::<bool>());
+        assert_eq!(meta.new(args).ok().unwrap().as_factory_of::<i16>().unwrap().take(), 26);
+    }
+
+    #[test]
+    fn should_return_errors_with_flattened_index() {
+        let meta = dependency_metafactory(|(a, b): (i32, bool)| if b { a } else { -a });
+
+        assert_eq!(
+            meta.new(vec![argless_as_factory(cloneable(5i32))]).err().unwrap(),
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 1))
+        );
+        assert_eq!(
+            meta.new(vec![argless_as_factory(cloneable(5i32)), argless_as_factory(cloneable(5i32))]).err().unwrap(),
+            FactoryErrorKind::ArgTypeMismatch(
+                ArgTypeMismatch::new(TypeDef::of::<bool>(), 1, TypeDef::of::<i32>())
+            )
+        );
+        assert!(meta.check_args(&[FactoryType::of::<i32>(), FactoryType::of::<bool>()]).is_ok());
+    }
+
+    #[test]
+    fn should_return_missing_argument_when_unwrapping_more_than_listed() {
+        let meta = dependency_metafactory(|_: Mismatched| true);
+
+        assert_eq!(
+            meta.new(vec![argless_as_factory(cloneable(5i32))]).err().unwrap(),
+            FactoryErrorKind::MissingArgument(MissingArgument::new(TypeDef::of::<bool>(), 1))
+        );
+    }
+}
@@ -1,46 +1,711 @@
-//! Closure argument error types.
+//! Closure argument and fallible factory error types.
+
+use std::error::Error;
+use std::fmt;
 
 use typedef::TypeDef;
 
+use crate::{ FactoryKind, FactoryType, TypeName };
+
 /// Specified argument count does not match metafactory argument count.
-#[derive(Copy)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct ArgCountMismatch {
-    pub expected: uint,
-    pub specified: uint,
+    pub expected: usize,
+    pub specified: usize,
 }
 
 /// Argument type did not match expected type.
-#[derive(Copy)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ArgTypeMismatch {
     pub expected_type: TypeDef,
-    pub argument_index: uint,
+    pub argument_index: usize,
+    pub actual_type: TypeDef,
+    /// Argument name, if metafactory knows it.
+    pub argument_name: Option<String>,
+    pub expected_type_name: TypeName,
+    pub actual_type_name: TypeName,
 }
 
 impl ArgCountMismatch {
     /// Convenience method for creating new `ArgCountMismatch`.
-    pub fn new(expected: uint, specified: uint) -> ArgCountMismatch {
+    pub fn new(expected: usize, specified: usize) -> ArgCountMismatch {
         ArgCountMismatch {
-            expected: expected,
-            specified: specified,
+            expected,
+            specified,
         }
     }
 }
 
 impl ArgTypeMismatch {
     /// Convenience method for creating new `ArgTypeMismatch`.
-    pub fn new(expected_type: TypeDef, argument_index: uint) -> ArgTypeMismatch {
+    pub fn new(expected_type: TypeDef, argument_index: usize, actual_type: TypeDef) -> ArgTypeMismatch {
         ArgTypeMismatch {
-            expected_type: expected_type,
-            argument_index: argument_index,
+            expected_type,
+            argument_index,
+            actual_type,
+            argument_name: None,
+            expected_type_name: TypeName::unknown(),
+            actual_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+/// Argument factory produces expected type, but is of another kind,
+/// for example a `TryFactory` specified for plain `Factory` argument.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgKindMismatch {
+    pub produced_type: TypeDef,
+    pub argument_index: usize,
+    pub expected_kind: FactoryKind,
+    pub actual_kind: FactoryKind,
+    /// Argument name, if metafactory knows it.
+    pub argument_name: Option<String>,
+    pub produced_type_name: TypeName,
+}
+
+impl ArgKindMismatch {
+    /// Convenience method for creating new `ArgKindMismatch`.
+    pub fn new(produced_type: TypeDef, argument_index: usize, expected_kind: FactoryKind, actual_kind: FactoryKind) -> ArgKindMismatch {
+        ArgKindMismatch {
+            produced_type,
+            argument_index,
+            expected_kind,
+            actual_kind,
+            argument_name: None,
+            produced_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+/// Required argument was not specified.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MissingArgument {
+    pub expected_type: TypeDef,
+    pub argument_index: usize,
+    /// Argument name, if metafactory knows it.
+    pub argument_name: Option<String>,
+    pub expected_type_name: TypeName,
+}
+
+impl MissingArgument {
+    /// Convenience method for creating new `MissingArgument`.
+    pub fn new(expected_type: TypeDef, argument_index: usize) -> MissingArgument {
+        MissingArgument {
+            expected_type,
+            argument_index,
+            argument_name: None,
+            expected_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+/// Named argument does not exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownArgument {
+    pub name: String,
+}
+
+impl UnknownArgument {
+    /// Convenience method for creating new `UnknownArgument`.
+    pub fn new(name: &str) -> UnknownArgument {
+        UnknownArgument {
+            name: name.to_string(),
+        }
+    }
+}
+
+/// Argument name is given to more than one argument.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateArgument {
+    pub name: String,
+}
+
+impl DuplicateArgument {
+    /// Convenience method for creating new `DuplicateArgument`.
+    pub fn new(name: &str) -> DuplicateArgument {
+        DuplicateArgument {
+            name: name.to_string(),
+        }
+    }
+}
+
+/// There is no argument at specified index.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ArgIndexOutOfRange {
+    pub index: usize,
+    pub arg_count: usize,
+}
+
+impl ArgIndexOutOfRange {
+    /// Convenience method for creating new `ArgIndexOutOfRange`.
+    pub fn new(index: usize, arg_count: usize) -> ArgIndexOutOfRange {
+        ArgIndexOutOfRange {
+            index,
+            arg_count,
+        }
+    }
+}
+
+/// Definition with specified name does not exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotRegistered {
+    pub name: String,
+}
+
+/// Produced value type did not match expected type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TypeMismatch {
+    pub expected_type: TypeDef,
+    pub actual_type: TypeDef,
+    pub expected_type_name: TypeName,
+    pub actual_type_name: TypeName,
+}
+
+/// Failed to build a factory for named definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefinitionError {
+    pub name: String,
+    pub error: Box<FactoryErrorKind>,
+}
+
+impl NotRegistered {
+    /// Convenience method for creating new `NotRegistered`.
+    pub fn new(name: &str) -> NotRegistered {
+        NotRegistered {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl TypeMismatch {
+    /// Convenience method for creating new `TypeMismatch`.
+    pub fn new(expected_type: TypeDef, actual_type: TypeDef) -> TypeMismatch {
+        TypeMismatch {
+            expected_type,
+            actual_type,
+            expected_type_name: TypeName::unknown(),
+            actual_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+impl DefinitionError {
+    /// Convenience method for creating new `DefinitionError`.
+    pub fn new(name: &str, error: FactoryErrorKind) -> DefinitionError {
+        DefinitionError {
+            name: name.to_string(),
+            error: Box::new(error),
+        }
+    }
+}
+
+/// No metafactory produces the required type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MissingProvider {
+    pub required_type: TypeDef,
+    pub required_type_name: TypeName,
+}
+
+/// More than one metafactory produces the required type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AmbiguousProvider {
+    pub required_type: TypeDef,
+    pub provider_count: usize,
+    pub required_type_name: TypeName,
+}
+
+impl MissingProvider {
+    /// Convenience method for creating new `MissingProvider`.
+    pub fn new(required_type: TypeDef) -> MissingProvider {
+        MissingProvider {
+            required_type,
+            required_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+impl AmbiguousProvider {
+    /// Convenience method for creating new `AmbiguousProvider`.
+    pub fn new(required_type: TypeDef, provider_count: usize) -> AmbiguousProvider {
+        AmbiguousProvider {
+            required_type,
+            provider_count,
+            required_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+/// Dependencies form a cycle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DependencyCycle {
+    /// Names or types in the cycle, starting and ending with the same item.
+    pub path: Vec<String>,
+}
+
+impl DependencyCycle {
+    /// Convenience method for creating new `DependencyCycle`.
+    pub fn new(path: Vec<String>) -> DependencyCycle {
+        DependencyCycle {
+            path,
+        }
+    }
+}
+
+/// Aggregate child factory does not produce the aggregated type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChildTypeMismatch {
+    pub expected_type: TypeDef,
+    pub child_index: usize,
+    pub actual_type: TypeDef,
+    pub expected_type_name: TypeName,
+    pub actual_type_name: TypeName,
+}
+
+impl ChildTypeMismatch {
+    /// Convenience method for creating new `ChildTypeMismatch`.
+    pub fn new(expected_type: TypeDef, child_index: usize, actual_type: TypeDef) -> ChildTypeMismatch {
+        ChildTypeMismatch {
+            expected_type,
+            child_index,
+            actual_type,
+            expected_type_name: TypeName::unknown(),
+            actual_type_name: TypeName::unknown(),
+        }
+    }
+}
+
+/// Aggregate child factory produces the aggregated type, but is of
+/// another kind than aggregate expects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChildKindMismatch {
+    pub produced_type: TypeDef,
+    pub child_index: usize,
+    pub expected_kind: FactoryKind,
+    pub actual_kind: FactoryKind,
+    pub produced_type_name: TypeName,
+}
+
+impl ChildKindMismatch {
+    /// Convenience method for creating new `ChildKindMismatch`.
+    pub fn new(produced_type: TypeDef, child_index: usize, expected_kind: FactoryKind, actual_kind: FactoryKind) -> ChildKindMismatch {
+        ChildKindMismatch {
+            produced_type,
+            child_index,
+            expected_kind,
+            actual_kind,
+            produced_type_name: TypeName::unknown(),
         }
     }
 }
 
 /// Getter creation error types.
-#[derive(Copy)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum FactoryErrorKind {
     /// Incorrect number of arguments.
     ArgCountMismatch(ArgCountMismatch),
     /// Incorrect argument type.
     ArgTypeMismatch(ArgTypeMismatch),
+    /// Incorrect argument factory kind.
+    ArgKindMismatch(ArgKindMismatch),
+    /// Required argument slot is empty.
+    MissingArgument(MissingArgument),
+    /// There is no argument with specified name.
+    UnknownArgument(UnknownArgument),
+    /// Several arguments have the same name.
+    DuplicateArgument(DuplicateArgument),
+    /// Argument index is not less than argument count.
+    ArgIndexOutOfRange(ArgIndexOutOfRange),
+    /// Definition is not registered.
+    NotRegistered(NotRegistered),
+    /// Produced value has incorrect type.
+    TypeMismatch(TypeMismatch),
+    /// Error in named definition or its dependencies.
+    DefinitionError(DefinitionError),
+    /// No metafactory produces required type.
+    MissingProvider(MissingProvider),
+    /// Several metafactories produce required type.
+    AmbiguousProvider(AmbiguousProvider),
+    /// Dependencies require each other.
+    DependencyCycle(DependencyCycle),
+    /// Incorrect aggregate child type.
+    ChildTypeMismatch(ChildTypeMismatch),
+    /// Incorrect aggregate child factory kind.
+    ChildKindMismatch(ChildKindMismatch),
+    /// Several errors at once.
+    Multiple(Vec<FactoryErrorKind>),
+}
+
+impl FactoryErrorKind {
+    /// Fill in argument names of argument errors.
+    ///
+    /// The `name_of` closure returns the name for argument index. Nested
+    /// errors of `Multiple` are named too.
+    pub fn with_arg_names<F>(self, name_of: &F) -> FactoryErrorKind
+        where F: Fn(usize) -> Option<String>
+    {
+        match self {
+            FactoryErrorKind::ArgTypeMismatch(mut e) => {
+                if e.argument_name.is_none() {
+                    e.argument_name = name_of(e.argument_index);
+                }
+                FactoryErrorKind::ArgTypeMismatch(e)
+            },
+            FactoryErrorKind::ArgKindMismatch(mut e) => {
+                if e.argument_name.is_none() {
+                    e.argument_name = name_of(e.argument_index);
+                }
+                FactoryErrorKind::ArgKindMismatch(e)
+            },
+            FactoryErrorKind::MissingArgument(mut e) => {
+                if e.argument_name.is_none() {
+                    e.argument_name = name_of(e.argument_index);
+                }
+                FactoryErrorKind::MissingArgument(e)
+            },
+            FactoryErrorKind::Multiple(errors) => FactoryErrorKind::Multiple(
+                errors.into_iter().map(|e| e.with_arg_names(name_of)).collect()
+            ),
+            other => other,
+        }
+    }
+
+    /// Combine a list of errors into a single error.
+    ///
+    /// Returns `None` for empty list, the error itself if there is only one,
+    /// and `Multiple` otherwise.
+    pub fn combine(mut errors: Vec<FactoryErrorKind>) -> Option<FactoryErrorKind> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(FactoryErrorKind::Multiple(errors)),
+        }
+    }
+}
+
+/// Error returned when a fallible factory fails to produce a value.
+///
+/// It contains the original error and records which node in the
+/// factory tree has failed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TryTakeError<E> {
+    /// Error returned by the failed node.
+    pub error: E,
+    /// Type of the value the failed node was supposed to produce.
+    pub failed_type: TypeDef,
+    pub failed_type_name: TypeName,
+    /// Argument indices leading from the root factory to the failed node.
+    ///
+    /// Empty if the root factory itself has failed.
+    pub path: Vec<usize>,
+}
+
+impl<E> TryTakeError<E> {
+    /// Convenience method for creating new `TryTakeError` for the failed node.
+    pub fn new(error: E, failed_type: TypeDef) -> TryTakeError<E> {
+        TryTakeError {
+            error,
+            failed_type,
+            failed_type_name: TypeName::unknown(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Create new `TryTakeError` for the failed node that produces `T`,
+    /// with readable type name.
+    pub fn of<T: 'static>(error: E) -> TryTakeError<E> {
+        TryTakeError {
+            failed_type_name: TypeName::of::<T>(),
+            ..TryTakeError::new(error, TypeDef::of::<T>())
+        }
+    }
+
+    /// Record that the failed node was reached through argument at
+    /// specified index.
+    pub fn within(mut self, argument_index: usize) -> TryTakeError<E> {
+        self.path.insert(0, argument_index);
+        self
+    }
+}
+
+impl fmt::Display for ArgCountMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} arguments, {} specified", self.expected, self.specified)
+    }
+}
+
+/// Write argument name if it is known, and index otherwise.
+fn fmt_argument(f: &mut fmt::Formatter, index: usize, name: &Option<String>) -> fmt::Result {
+    match *name {
+        Some(ref name) => write!(f, "argument \"{}\"", name),
+        None => write!(f, "argument {}", index),
+    }
+}
+
+impl fmt::Display for ArgTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_argument(f, self.argument_index, &self.argument_name)?;
+        write!(
+            f, " expected {}, got {}",
+            self.expected_type_name.describe(self.expected_type),
+            self.actual_type_name.describe(self.actual_type)
+        )
+    }
+}
+
+impl fmt::Display for ArgKindMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_argument(f, self.argument_index, &self.argument_name)?;
+        write!(
+            f, " expected {}, got {}",
+            FactoryType::new(self.produced_type, self.expected_kind).with_type_name(self.produced_type_name),
+            FactoryType::new(self.produced_type, self.actual_kind).with_type_name(self.produced_type_name)
+        )
+    }
+}
+
+impl fmt::Display for MissingArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_argument(f, self.argument_index, &self.argument_name)?;
+        write!(f, " expected {}, got nothing", self.expected_type_name.describe(self.expected_type))
+    }
+}
+
+impl fmt::Display for UnknownArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown argument \"{}\"", self.name)
+    }
+}
+
+impl fmt::Display for DuplicateArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "argument name \"{}\" is used more than once", self.name)
+    }
+}
+
+impl fmt::Display for ArgIndexOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "argument index {} is out of range for {} arguments", self.index, self.arg_count)
+    }
+}
+
+impl fmt::Display for NotRegistered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\" is not registered", self.name)
+    }
+}
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "expected {}, got {}",
+            self.expected_type_name.describe(self.expected_type),
+            self.actual_type_name.describe(self.actual_type)
+        )
+    }
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed to build \"{}\": {}", self.name, self.error)
+    }
+}
+
+impl fmt::Display for MissingProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no provider for {}", self.required_type_name.describe(self.required_type))
+    }
+}
+
+impl fmt::Display for AmbiguousProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} providers for {}", self.provider_count, self.required_type_name.describe(self.required_type))
+    }
+}
+
+impl fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dependency cycle {}", self.path.join(" -> "))
+    }
+}
+
+impl fmt::Display for ChildTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "aggregate child {} expected {}, got {}", self.child_index,
+            self.expected_type_name.describe(self.expected_type),
+            self.actual_type_name.describe(self.actual_type)
+        )
+    }
+}
+
+impl fmt::Display for ChildKindMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f, "aggregate child {} expected {}, got {}", self.child_index,
+            FactoryType::new(self.produced_type, self.expected_kind).with_type_name(self.produced_type_name),
+            FactoryType::new(self.produced_type, self.actual_kind).with_type_name(self.produced_type_name)
+        )
+    }
+}
+
+impl fmt::Display for FactoryErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FactoryErrorKind::ArgCountMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::ArgTypeMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::ArgKindMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::MissingArgument(ref e) => e.fmt(f),
+            FactoryErrorKind::UnknownArgument(ref e) => e.fmt(f),
+            FactoryErrorKind::DuplicateArgument(ref e) => e.fmt(f),
+            FactoryErrorKind::ArgIndexOutOfRange(ref e) => e.fmt(f),
+            FactoryErrorKind::NotRegistered(ref e) => e.fmt(f),
+            FactoryErrorKind::TypeMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::DefinitionError(ref e) => e.fmt(f),
+            FactoryErrorKind::MissingProvider(ref e) => e.fmt(f),
+            FactoryErrorKind::AmbiguousProvider(ref e) => e.fmt(f),
+            FactoryErrorKind::DependencyCycle(ref e) => e.fmt(f),
+            FactoryErrorKind::ChildTypeMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::ChildKindMismatch(ref e) => e.fmt(f),
+            FactoryErrorKind::Multiple(ref errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("; "))
+            },
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TryTakeError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "failed to produce {}", self.failed_type_name.describe(self.failed_type))?;
+        if !self.path.is_empty() {
+            let path: Vec<String> = self.path.iter().map(|i| i.to_string()).collect();
+            write!(f, " at argument path {}", path.join("."))?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl Error for ArgCountMismatch {}
+impl Error for ArgTypeMismatch {}
+impl Error for ArgKindMismatch {}
+impl Error for MissingArgument {}
+impl Error for UnknownArgument {}
+impl Error for DuplicateArgument {}
+impl Error for ArgIndexOutOfRange {}
+impl Error for NotRegistered {}
+impl Error for TypeMismatch {}
+impl Error for MissingProvider {}
+impl Error for AmbiguousProvider {}
+impl Error for DependencyCycle {}
+impl Error for ChildTypeMismatch {}
+impl Error for ChildKindMismatch {}
+
+impl Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+impl Error for FactoryErrorKind {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FactoryErrorKind::DefinitionError(ref e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for TryTakeError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use typedef::TypeDef;
+    use crate::TypeName;
+    use super::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch, DefinitionError, DependencyCycle, TryTakeError };
+
+    fn i8_for_f64(argument_index: usize) -> ArgTypeMismatch {
+        ArgTypeMismatch {
+            expected_type_name: TypeName::of::<i8>(),
+            actual_type_name: TypeName::of::<f64>(),
+            ..ArgTypeMismatch::new(TypeDef::of::<i8>(), argument_index, TypeDef::of::<f64>())
+        }
+    }
+
+    #[test]
+    fn should_display_arg_count_mismatch() {
+        assert_eq!(
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(3, 2)).to_string(),
+            "expected 3 arguments, 2 specified"
+        );
+    }
+
+    #[test]
+    fn should_display_arg_type_mismatch_with_type_name() {
+        assert_eq!(i8_for_f64(2).to_string(), "argument 2 expected i8, got f64");
+    }
+
+    #[test]
+    fn should_display_argument_name_instead_of_index() {
+        let error = FactoryErrorKind::ArgTypeMismatch(i8_for_f64(1))
+            .with_arg_names(&|index| if index == 1 { Some("height".to_string()) } else { None });
+
+        assert_eq!(error.to_string(), "argument \"height\" expected i8, got f64");
+    }
+
+    #[test]
+    fn should_display_dependency_cycle() {
+        let cycle = DependencyCycle::new(vec!["a".to_string(), "b".to_string(), "a".to_string()]);
+        assert_eq!(cycle.to_string(), "dependency cycle a -> b -> a");
+    }
+
+    #[test]
+    fn should_display_try_take_error_path() {
+        let error = TryTakeError::of::<u8>("nope").within(1).within(0);
+        assert_eq!(error.to_string(), "failed to produce u8 at argument path 0.1: nope");
+    }
+
+    #[test]
+    fn should_combine_errors() {
+        let count = FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(3, 2));
+        let cycle = FactoryErrorKind::DependencyCycle(DependencyCycle::new(vec!["a".to_string(), "a".to_string()]));
+
+        assert_eq!(FactoryErrorKind::combine(Vec::new()), None);
+        assert_eq!(FactoryErrorKind::combine(vec![count.clone()]), Some(count.clone()));
+        assert_eq!(
+            FactoryErrorKind::combine(vec![count.clone(), cycle.clone()]).unwrap().to_string(),
+            "expected 3 arguments, 2 specified; dependency cycle a -> a"
+        );
+    }
+
+    #[test]
+    fn should_compare_errors() {
+        assert_eq!(
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(1, 2)),
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(1, 2))
+        );
+        assert!(
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(1, 2))
+            != FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 2))
+        );
+    }
+
+    #[test]
+    fn should_be_usable_as_boxed_error() {
+        fn fails() -> Result<(), Box<dyn Error>> {
+            Err(FactoryErrorKind::DefinitionError(DefinitionError::new(
+                "sum",
+                FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 1))
+            )))?;
+            Ok(())
+        }
+
+        let error = fails().err().unwrap();
+        assert_eq!(error.to_string(), "failed to build \"sum\": expected 2 arguments, 1 specified");
+        assert!(error.source().is_some());
+    }
 }
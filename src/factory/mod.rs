@@ -1,9 +1,108 @@
 //! Implements a wrapper struct container for internal getter.
+//!
+//! Also contains getters used by `Factory` combinators. Each of them
+//! keeps the source factories and a shared closure reference, so the
+//! combined factory can be cloned like any other.
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use crate::{ Factory, Getter };
+
+/// Getter that transforms values of the source factory.
+pub struct Map<T: 'static, F> {
+    pub source: Factory<T>,
+    pub closure: Rc<RefCell<F>>,
+}
+
+impl<T: 'static, U: 'static, F> Getter<U> for Map<T, F>
+    where F: FnMut(T) -> U + 'static
+{
+    fn take(&self) -> U {
+        let value = self.source.take();
+        (*self.closure.borrow_mut())(value)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<U>> {
+        Box::new(Map {
+            source: self.source.clone(),
+            closure: self.closure.clone(),
+        })
+    }
+}
+
+/// Getter that produces values of two factories as a pair.
+pub struct Zip<T: 'static, U: 'static> {
+    pub first: Factory<T>,
+    pub second: Factory<U>,
+}
+
+impl<T: 'static, U: 'static> Getter<(T, U)> for Zip<T, U> {
+    fn take(&self) -> (T, U) {
+        (self.first.take(), self.second.take())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<(T, U)>> {
+        Box::new(Zip {
+            first: self.first.clone(),
+            second: self.second.clone(),
+        })
+    }
+}
+
+/// Getter that produces a value from the factory returned by closure.
+pub struct AndThen<T: 'static, F> {
+    pub source: Factory<T>,
+    pub closure: Rc<RefCell<F>>,
+}
+
+impl<T: 'static, U: 'static, F> Getter<U> for AndThen<T, F>
+    where F: FnMut(T) -> Factory<U> + 'static
+{
+    fn take(&self) -> U {
+        let value = self.source.take();
+        let factory = (*self.closure.borrow_mut())(value);
+        factory.take()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<U>> {
+        Box::new(AndThen {
+            source: self.source.clone(),
+            closure: self.closure.clone(),
+        })
+    }
+}
+
+/// Getter that passes a reference to every produced value to closure.
+pub struct Inspect<T: 'static, F> {
+    pub source: Factory<T>,
+    pub closure: Rc<RefCell<F>>,
+}
+
+impl<T: 'static, F> Getter<T> for Inspect<T, F>
+    where F: FnMut(&T) + 'static
+{
+    fn take(&self) -> T {
+        let value = self.source.take();
+        (*self.closure.borrow_mut())(&value);
+        value
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(Inspect {
+            source: self.source.clone(),
+            closure: self.closure.clone(),
+        })
+    }
+}
 
 #[cfg(test)]
 mod test {
-    use { Getter, Factory, AsFactoryExt };
+    use typedef::TypeDef;
+    use crate::{ Getter, Factory, AnyFactory, AsFactoryExt };
     use std::any::Any;
+    use std::rc::Rc;
+    use std::cell::RefCell;
 
     #[test]
     fn should_get_correct_value() {
@@ -21,14 +120,57 @@ mod test {
 
     #[test]
     fn should_be_able_to_downcast_from_any() {
-        let boxany = box create_with_val("HAI") as Box<Any>;
+        let boxany = Box::new(create_with_val("HAI")) as Box<dyn Any>;
         let downcasted = boxany.as_factory_of::<String>().unwrap();
 
         assert_eq!(downcasted.take(), "HAI");
     }
 
+    #[test]
+    fn any_factory_should_know_produced_type() {
+        let anyfactory = AnyFactory::from(create_with_val("HAI"));
+        assert_eq!(anyfactory.get_type(), TypeDef::of::<String>());
+
+        let downcasted = anyfactory.as_factory_of::<String>().unwrap();
+        assert_eq!(downcasted.take(), "HAI");
+    }
+
+    #[test]
+    fn should_map_produced_value() {
+        let factory = create_with_val("HAI").map(|s| s.len());
+
+        assert_eq!(factory.take(), 3);
+        assert_eq!(factory.clone().take(), 3);
+    }
+
+    #[test]
+    fn should_zip_two_factories() {
+        let factory = create_with_val("HAI").zip(create_with_val("BAI").map(|s| s.to_lowercase()));
+
+        assert_eq!(factory.clone().take(), ("HAI".to_string(), "bai".to_string()));
+    }
+
+    #[test]
+    fn should_take_value_from_returned_factory() {
+        let factory = create_with_val("HAI").and_then(|s| create_with_val(&format!("{}!", s)));
+
+        assert_eq!(factory.take(), "HAI!");
+        assert_eq!(factory.clone().take(), "HAI!");
+    }
+
+    #[test]
+    fn should_inspect_values_of_all_clones() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let seen_by_closure = seen.clone();
+        let factory = create_with_val("HAI").inspect(move |s| seen_by_closure.borrow_mut().push(s.clone()));
+
+        assert_eq!(factory.take(), "HAI");
+        assert_eq!(factory.clone().take(), "HAI");
+        assert_eq!(*seen.borrow(), vec!["HAI", "HAI"]);
+    }
+
     fn create_with_val(val: &str) -> Factory<String> {
-        Factory::new(box ValContainer { val: val.to_string() })
+        Factory::new(Box::new(ValContainer { val: val.to_string() }))
     }
 
     struct ValContainer {
@@ -40,10 +182,10 @@ mod test {
             self.val.clone()
         }
 
-        fn boxed_clone<'r>(&self) -> Box<Getter<String> + 'r> {
-            box ValContainer {
+        fn boxed_clone(&self) -> Box<dyn Getter<String>> {
+            Box::new(ValContainer {
                 val: self.val.clone(),
-            }
+            })
         }
     }
 }
@@ -0,0 +1,270 @@
+//! This implements fallible metafactory construction from a closure.
+//!
+//! Supports closures with up to 12 arguments, same as regular closures.
+
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, ArgDescriptor, AsFactoryExt, FactoryKind, FactoryType };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, MissingArgument, TryTakeError };
+use crate::aggregate::Aggregate;
+use super::{ ToTryMetaFactory, TryFactory, TryGetter };
+
+#[macro_use]
+mod macros {
+    macro_rules! try_closure_impl(
+        ($ClosureScope:ident, $GetterScope:ident: $($_A:ident,$_AT:ty,$_a:ident,$index:tt)|*)
+        =>
+        (
+            /// Shared closure reference, used as `MetaFactory`.
+            struct $ClosureScope<F, $($_A,)* T, E> {
+                closure: Rc<RefCell<F>>,
+                _marker: PhantomData<fn($($_AT),*) -> Result<T, E>>,
+            }
+
+            /// Contains references to parent factories, so they
+            /// can be invoked directly to get arguments for the closure.
+            struct $GetterScope<F, $($_A:'static,)* T:'static, E:'static> {
+                // References to argument factories.
+                $(
+                    $_a: TryFactory<$_AT, E>,
+                )*
+                // Closure reference.
+                closure: Rc<RefCell<F>>,
+                _marker: PhantomData<fn() -> Result<T, E>>,
+            }
+
+            /// Implement `ToTryMetaFactory` conversion for closures
+            /// FnMut(A1, A2, ... AN) -> Result<T, E>
+            impl<F, $($_A:'static,)* T:'static, E:'static> ToTryMetaFactory<fn($($_AT),*) -> Result<T, E>> for F
+                where F: FnMut($($_AT),*) -> Result<T, E> + 'static
+            {
+                fn to_try_metafactory(self) -> Box<dyn MetaFactory> {
+                    Box::new($ClosureScope::<F, $($_AT,)* T, E> {
+                        closure: Rc::new(RefCell::new(self)),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+
+            impl<F, $($_A:'static,)* T:'static, E:'static> MetaFactory for $ClosureScope<F, $($_AT,)* T, E>
+                where F: FnMut($($_AT),*) -> Result<T, E> + 'static
+            {
+                fn get_type(&self) -> TypeDef {
+                    TypeDef::of::<T>()
+                }
+
+                fn get_arg_types(&self) -> Vec<TypeDef> {
+                    vec![$(TypeDef::of::<$_AT>()),*]
+                }
+
+                fn get_factory_type(&self) -> FactoryType {
+                    FactoryType::try_of::<T, E>()
+                }
+
+                /// Arguments expect `TryFactory<A, E>`, and also accept
+                /// factories of `A` and `Result<A, E>`, same as `new`.
+                fn get_args(&self) -> Vec<ArgDescriptor> {
+                    vec![$(
+                        ArgDescriptor::of::<$_AT>()
+                            .with_kind(FactoryKind::fallible::<E>())
+                            .accepting(FactoryType::of::<$_AT>())
+                            .accepting(FactoryType::of::<Result<$_AT, E>>())
+                    ),*]
+                }
+
+                #[allow(unused_assignments, unused_mut, unused_variables)]
+                fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+                    let required_argc = count_exprs!($($_a),*);
+
+                    assert_arg_count!(required_argc, arg_getters.len());
+
+                    let mut getters = arg_getters.into_iter();
+                    let mut arg_index = 0;
+                    $(
+                        let $_a;
+                        {
+                            let maybe_factory = getters.next().unwrap();
+
+                            $_a = try_unwrap_factory!($_AT, maybe_factory, arg_index, FactoryKind::fallible::<E>(), as_try_factory_of, E);
+
+                            arg_index += 1;
+                        }
+                    )*
+
+                    let factory = AnyFactory::from(TryFactory::<T, E>::new(
+                        Box::new($GetterScope::<F, $($_AT,)* T, E> {
+                            $(
+                                $_a,
+                            )*
+                            closure: self.closure.clone(),
+                            _marker: PhantomData,
+                        })
+                    ));
+
+                    Ok(factory)
+                }
+
+                fn new_aggregate(&self) -> Aggregate<'static> {
+                    Aggregate::new_try::<T, E>()
+                }
+            }
+
+            impl<F, $($_A:'static,)* T: 'static, E: 'static> TryGetter<T, E> for $GetterScope<F, $($_AT,)* T, E>
+                where F: FnMut($($_AT),*) -> Result<T, E> + 'static
+            {
+                fn try_take(&self) -> Result<T, TryTakeError<E>> {
+                    // The first failed argument stops the construction.
+                    $(
+                        let $_a = self.$_a.try_take().map_err(|e| e.within($index))?;
+                    )*
+                    (*self.closure.borrow_mut())($($_a),*)
+                        .map_err(TryTakeError::of::<T>)
+                }
+
+                fn boxed_clone(&self) -> Box<dyn TryGetter<T, E>> {
+                    Box::new($GetterScope::<F, $($_AT,)* T, E> {
+                        $(
+                            $_a: self.$_a.clone(),
+                        )*
+                        closure: self.closure.clone(),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+        )
+    );
+}
+
+try_closure_impl!(
+    ClosureScope0, GetterScope0:
+);
+
+try_closure_impl!(
+    ClosureScope1, GetterScope1:
+    A1, A1, a1, 0
+);
+
+try_closure_impl!(
+    ClosureScope2, GetterScope2:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1
+);
+
+try_closure_impl!(
+    ClosureScope3, GetterScope3:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2
+);
+
+try_closure_impl!(
+    ClosureScope4, GetterScope4:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3
+);
+
+try_closure_impl!(
+    ClosureScope5, GetterScope5:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4
+);
+
+try_closure_impl!(
+    ClosureScope6, GetterScope6:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5
+);
+
+try_closure_impl!(
+    ClosureScope7, GetterScope7:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6
+);
+
+try_closure_impl!(
+    ClosureScope8, GetterScope8:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6 |
+    A8, A8, a8, 7
+);
+
+try_closure_impl!(
+    ClosureScope9, GetterScope9:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6 |
+    A8, A8, a8, 7 |
+    A9, A9, a9, 8
+);
+
+try_closure_impl!(
+    ClosureScope10, GetterScope10:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6 |
+    A8, A8, a8, 7 |
+    A9, A9, a9, 8 |
+    A10, A10, a10, 9
+);
+
+try_closure_impl!(
+    ClosureScope11, GetterScope11:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6 |
+    A8, A8, a8, 7 |
+    A9, A9, a9, 8 |
+    A10, A10, a10, 9 |
+    A11, A11, a11, 10
+);
+
+try_closure_impl!(
+    ClosureScope12, GetterScope12:
+    A1, A1, a1, 0 |
+    A2, A2, a2, 1 |
+    A3, A3, a3, 2 |
+    A4, A4, a4, 3 |
+    A5, A5, a5, 4 |
+    A6, A6, a6, 5 |
+    A7, A7, a7, 6 |
+    A8, A8, a8, 7 |
+    A9, A9, a9, 8 |
+    A10, A10, a10, 9 |
+    A11, A11, a11, 10 |
+    A12, A12, a12, 11
+);
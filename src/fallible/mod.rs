@@ -0,0 +1,249 @@
+//! Factories that can fail to produce a value.
+//!
+//! A closure that returns `Result<T, E>` can be converted to a fallible
+//! metafactory with `try_metafactory`. It reports `T` as its type, and
+//! builds `TryFactory<T, E>` instances.
+//!
+//! Arguments of such closure can be other fallible factories, as well as
+//! plain `Factory<A>` or `Factory<Result<A, E>>`. If any argument fails,
+//! the closure is not invoked, and the error is returned from `try_take`
+//! together with the path to the failed node in the factory tree.
+//!
+//! ```
+//! use metafactory::{ argless_as_factory, AsFactoryExt };
+//! use metafactory::fallible::try_metafactory;
+//!
+//! fn main() {
+//!     let meta_parse = try_metafactory(|s: &'static str| s.parse::<i32>().map_err(|_| ()));
+//!     let meta_sum = try_metafactory(|a: i32, b: i32| a.checked_add(b).ok_or(()));
+//!
+//!     let sum = meta_sum.new(vec![
+//!         meta_parse.new(vec![argless_as_factory(|| "5")]).ok().unwrap(),
+//!         meta_parse.new(vec![argless_as_factory(|| "six")]).ok().unwrap(),
+//!     ]).ok().unwrap().as_try_factory_of::<i32, ()>().unwrap();
+//!
+//!     let error = sum.try_take().err().unwrap();
+//!
+//!     // the second argument of the sum has failed.
+//!     assert_eq!(error.path, vec![1]);
+//!     assert!(error.failed_type.is::<i32>());
+//! }
+//! ```
+
+use crate::{ Factory, MetaFactory };
+use crate::error::{ TryTakeError };
+
+mod from_closure;
+
+/// Fallible gettable value trait.
+pub trait TryGetter<T, E> {
+    /// Produce a new value or return an error.
+    fn try_take(&self) -> Result<T, TryTakeError<E>>;
+
+    /// Create a clone for this getter.
+    fn boxed_clone(&self) -> Box<dyn TryGetter<T, E>>;
+}
+
+/// A fallible factory proxy.
+///
+/// Same as `Factory`, but the value creation can fail.
+pub struct TryFactory<T: 'static, E: 'static> {
+    getter: Box<dyn TryGetter<T, E>>,
+}
+
+impl<T: 'static, E: 'static> TryFactory<T, E> {
+    /// Create a new `TryFactory`.
+    ///
+    /// Create a new factory from any type that implements `TryGetter` trait.
+    pub fn new(getter: Box<dyn TryGetter<T, E>>) -> TryFactory<T, E> {
+        TryFactory::<T, E> {
+            getter,
+        }
+    }
+
+    /// Create a `TryFactory` that never fails from a plain `Factory`.
+    pub fn from_factory(factory: Factory<T>) -> TryFactory<T, E> {
+        TryFactory::new(Box::new(Infallible { factory }))
+    }
+
+    /// Create a `TryFactory` from a `Factory` that produces `Result`.
+    pub fn from_result_factory(factory: Factory<Result<T, E>>) -> TryFactory<T, E> {
+        TryFactory::new(Box::new(FromResult { factory }))
+    }
+
+    /// Get a new owned value, or the error that prevented creating it.
+    pub fn try_take(&self) -> Result<T, TryTakeError<E>> {
+        self.getter.try_take()
+    }
+}
+
+impl<T: 'static, E: 'static> Clone for TryFactory<T, E> {
+    fn clone(&self) -> TryFactory<T, E> {
+        TryFactory::<T, E> {
+            getter: self.getter.boxed_clone(),
+        }
+    }
+}
+
+/// Trait for values convertable to fallible `MetaFactory`.
+pub trait ToTryMetaFactory<Args> {
+    /// Creates a `MetaFactory` that produces `TryFactory` instances.
+    fn to_try_metafactory(self) -> Box<dyn MetaFactory>;
+}
+
+/// Create a new fallible `MetaFactory` from a closure that returns `Result`.
+pub fn try_metafactory<Args, T: ToTryMetaFactory<Args>>(any: T) -> Box<dyn MetaFactory> {
+    any.to_try_metafactory()
+}
+
+struct Infallible<T: 'static> {
+    factory: Factory<T>,
+}
+
+impl<T: 'static, E: 'static> TryGetter<T, E> for Infallible<T> {
+    fn try_take(&self) -> Result<T, TryTakeError<E>> {
+        Ok(self.factory.take())
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TryGetter<T, E>> {
+        Box::new(Infallible { factory: self.factory.clone() })
+    }
+}
+
+struct FromResult<T: 'static, E: 'static> {
+    factory: Factory<Result<T, E>>,
+}
+
+impl<T: 'static, E: 'static> TryGetter<T, E> for FromResult<T, E> {
+    fn try_take(&self) -> Result<T, TryTakeError<E>> {
+        self.factory.take()
+            .map_err(TryTakeError::of::<T>)
+    }
+
+    fn boxed_clone(&self) -> Box<dyn TryGetter<T, E>> {
+        Box::new(FromResult { factory: self.factory.clone() })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use typedef::TypeDef;
+    use crate::{ argless_as_factory, cloneable, FactoryType, AsFactoryExt };
+    use crate::aggregate::Aggregate;
+    use crate::error::FactoryErrorKind;
+    use super::try_metafactory;
+
+    #[test]
+    fn should_produce_value_when_nothing_fails() {
+        let factory = try_metafactory(|a: i32, b: i32| -> Result<i32, String> { Ok(a + b) })
+            .new(vec![
+                argless_as_factory(cloneable(1i32)),
+                argless_as_factory(|| 2i32),
+            ]).ok().unwrap()
+            .as_try_factory_of::<i32, String>().unwrap();
+
+        assert_eq!(factory.try_take().ok().unwrap(), 3);
+        assert_eq!(factory.clone().try_take().ok().unwrap(), 3);
+    }
+
+    #[test]
+    fn should_report_failed_root() {
+        let factory = try_metafactory(|| -> Result<bool, &'static str> { Err("no") })
+            .new(Vec::new()).ok().unwrap()
+            .as_try_factory_of::<bool, &'static str>().unwrap();
+
+        let error = factory.try_take().err().unwrap();
+        assert_eq!(error.error, "no");
+        assert_eq!(error.failed_type, TypeDef::of::<bool>());
+        assert!(error.path.is_empty());
+    }
+
+    #[test]
+    fn should_short_circuit_and_report_nested_path() {
+        let meta_fail = try_metafactory(|| -> Result<u8, &'static str> { Err("deep") });
+        let meta_wrap = try_metafactory(|a: bool, b: u8| -> Result<u8, &'static str> {
+            Ok(if a { b } else { 0 })
+        });
+        let meta_root = try_metafactory(|_a: u8, _b: u8| -> Result<String, &'static str> {
+            panic!("root closure should not be invoked")
+        });
+
+        let factory = meta_root.new(vec![
+            argless_as_factory(cloneable(1u8)),
+            meta_wrap.new(vec![
+                argless_as_factory(cloneable(true)),
+                meta_fail.new(Vec::new()).ok().unwrap(),
+            ]).ok().unwrap(),
+        ]).ok().unwrap()
+            .as_try_factory_of::<String, &'static str>().unwrap();
+
+        let error = factory.try_take().err().unwrap();
+        assert_eq!(error.error, "deep");
+        assert_eq!(error.failed_type, TypeDef::of::<u8>());
+        assert_eq!(error.path, vec![1, 1]);
+    }
+
+    #[test]
+    fn should_accept_result_factory_as_argument() {
+        let factory = try_metafactory(|a: i32| -> Result<i32, String> { Ok(a * 2) })
+            .new(vec![
+                argless_as_factory(|| -> Result<i32, String> { Err("bad".to_string()) }),
+            ]).ok().unwrap()
+            .as_try_factory_of::<i32, String>().unwrap();
+
+        let error = factory.try_take().err().unwrap();
+        assert_eq!(error.error, "bad");
+        assert_eq!(error.path, vec![0]);
+    }
+
+    #[test]
+    fn should_check_result_factory_as_argument() {
+        let meta = try_metafactory(|a: i32| -> Result<i32, String> { Ok(a) });
+
+        assert!(meta.check_args(&[FactoryType::of::<i32>()]).is_ok());
+        assert!(meta.check_args(&[FactoryType::of::<Result<i32, String>>()]).is_ok());
+        assert!(meta.check_args(&[FactoryType::of::<Result<i32, bool>>()]).is_err());
+        assert!(meta.check_args(&[FactoryType::try_of::<i32, String>()]).is_ok());
+        assert!(meta.check_args(&[FactoryType::try_of::<i32, bool>()]).is_err());
+    }
+
+    #[test]
+    fn should_return_arg_type_mismatch_for_bad_arg() {
+        match try_metafactory(|a: i32| -> Result<i32, String> { Ok(a) })
+            .new(vec![argless_as_factory(cloneable(true))])
+        {
+            Err(FactoryErrorKind::ArgTypeMismatch(e)) => {
+                assert_eq!(e.expected_type, TypeDef::of::<i32>());
+                assert_eq!(e.argument_index, 0);
+            },
+            _ => panic!("Expected ArgTypeMismatch error!"),
+        }
+    }
+
+    #[test]
+    fn should_aggregate_fallible_factories() {
+        let aggregate = Aggregate::new_try::<i32, ()>();
+
+        let factory = aggregate
+            .new_factory(vec![
+                argless_as_factory(cloneable(1i32)),
+                try_metafactory(|| -> Result<i32, ()> { Err(()) }).new(Vec::new()).ok().unwrap(),
+            ]).ok().unwrap()
+            .as_try_factory_of::<Vec<i32>, ()>().unwrap();
+
+        assert_eq!(factory.try_take().err().unwrap().path, vec![1]);
+    }
+
+    #[test]
+    fn should_aggregate_fallible_factories_by_key() {
+        let factory = Aggregate::new_try_map::<&'static str, i32, ()>()
+            .new_factory(vec![
+                ("one", argless_as_factory(cloneable(1i32))),
+                ("two", try_metafactory(|| -> Result<i32, ()> { Ok(2) }).new(Vec::new()).ok().unwrap()),
+            ]).ok().unwrap()
+            .as_try_factory_of::<HashMap<&'static str, i32>, ()>().unwrap();
+
+        assert_eq!(factory.try_take().ok().unwrap()["two"], 2);
+    }
+}
@@ -1,12 +1,15 @@
 //! This implements metafactory construction from cloneable value.
 //!
+//! Closures can be cloneable too, so the value has to be wrapped with
+//! `cloneable` to be used as a source.
+//!
 //! ```
-//! use metafactory::metafactory;
+//! use metafactory::{ metafactory, cloneable };
 //! use metafactory::AsFactoryExt;
 //!
 //! fn main() {
 //!     // build a metafactory from cloneable value.
-//!     let meta_factory = metafactory("hello");
+//!     let meta_factory = metafactory(cloneable("hello"));
 //!
 //!     // create a factory instance this closure.
 //!     let factory = meta_factory
@@ -19,28 +22,34 @@
 //! ```
 
 use typedef::{ TypeDef };
-use std::any::{ Any };
 
-use super::{ MetaFactory, ToMetaFactory };
-use super::{ Factory, Getter };
-use super::error::{ FactoryErrorKind };
-use aggregate::Aggregate;
+use crate::{ MetaFactory, ToMetaFactory };
+use crate::{ AnyFactory, Factory, FactoryType, Getter };
+use crate::error::FactoryErrorKind;
+use crate::aggregate::Aggregate;
+
+/// Cloneable value usable as a `MetaFactory` source.
+pub struct Cloneable<T> {
+    pub value: T,
+}
+
+/// Wrap a cloneable value so it can be used as a `MetaFactory` source.
+pub fn cloneable<T: 'static + Clone>(value: T) -> Cloneable<T> {
+    Cloneable { value }
+}
 
-#[experimental]
 struct CloneableMetaFactory<T> {
     pub value: T,
 }
 
-#[experimental]
 struct CloneableValue<T> {
     pub value: T,
 }
 
 /// Creates `MetaFactory` for cloneable value.
-#[stable]
-impl<T: 'static + Clone> ToMetaFactory for T {
-    fn to_metafactory<'a>(self) -> Box<MetaFactory + 'a> {
-        box CloneableMetaFactory { value : self }
+impl<T: 'static + Clone> ToMetaFactory<Cloneable<T>> for Cloneable<T> {
+    fn to_metafactory(self) -> Box<dyn MetaFactory> {
+        Box::new(CloneableMetaFactory { value : self.value })
     }
 }
 
@@ -53,11 +62,15 @@ impl<T: 'static + Clone> MetaFactory for CloneableMetaFactory<T> {
         Vec::new()
     }
 
-    fn new(&self, _arg_getters: Vec<Box<Any>>) -> Result<Box<Any>, FactoryErrorKind> {
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::of::<T>()
+    }
+
+    fn new(&self, _arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
         Ok(
-            box Factory::new(
-                box CloneableValue::<T> { value: self.value.clone() }
-            ) as Box<Any>
+            AnyFactory::from(Factory::new(
+                Box::new(CloneableValue::<T> { value: self.value.clone() })
+            ))
         )
     }
 
@@ -71,32 +84,32 @@ impl<T: 'static + Clone> Getter<T> for CloneableValue<T> {
         self.value.clone()
     }
 
-    fn boxed_clone(&self) -> Box<Getter<T> + 'static> {
-        box CloneableValue::<T> { value: self.value.clone() }
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(CloneableValue::<T> { value: self.value.clone() })
     }
 }
 
 #[cfg(test)]
 mod test {
     use typedef::TypeDef;
-    use super::super::{ ToMetaFactory, MetaFactory, AsFactoryExt };
+    use crate::{ ToMetaFactory, MetaFactory, FactoryType, AsFactoryExt, cloneable };
 
     #[test]
     fn should_return_correct_type() {
         assert_eq!(
-            create(24i).get_type(),
-            TypeDef::of::<int>()
+            create(cloneable(24i32)).get_type(),
+            TypeDef::of::<i32>()
         );
         assert_eq!(
-            create(1f32).get_type(),
+            create(cloneable(1f32)).get_type(),
             TypeDef::of::<f32>()
         );
         assert_eq!(
-            create("aaa".to_string()).get_type(),
+            create(cloneable("aaa".to_string())).get_type(),
             TypeDef::of::<String>()
         );
         assert_eq!(
-            create(box "aaa".to_string()).get_type(),
+            create(cloneable(Box::new("aaa".to_string()))).get_type(),
             TypeDef::of::<Box<String>>()
         );
     }
@@ -104,29 +117,39 @@ mod test {
     #[test]
     fn should_require_no_arguments() {
         assert_eq!(
-            create(24i).get_arg_types().len(),
+            create(cloneable(24i32)).get_arg_types().len(),
             0
         );
+        assert!(create(cloneable(24i32)).check_args(&[]).is_ok());
+        assert!(create(cloneable(24i32)).check_args(&[FactoryType::of::<i32>()]).is_err());
     }
 
     #[test]
     fn should_build_usable_factory() {
         assert_eq!(
-            create(24i).new(Vec::new()).ok().unwrap().as_factory_of::<int>().unwrap().take(),
-            24i
+            create(cloneable(24i32)).new(Vec::new()).ok().unwrap().as_factory_of::<i32>().unwrap().take(),
+            24i32
         );
     }
 
+    #[test]
+    fn should_accept_cloneable_closure_as_value() {
+        let meta = create(cloneable(|| 24i32));
+        let factory = meta.new(Vec::new()).ok().unwrap();
+
+        assert!(factory.as_factory_of::<i32>().is_none());
+    }
+
     #[test]
     fn factory_clone_should_return_same_value() {
-        let factory = create(24i).new(Vec::new()).ok().unwrap().as_factory_of::<int>().unwrap();
+        let factory = create(cloneable(24i32)).new(Vec::new()).ok().unwrap().as_factory_of::<i32>().unwrap();
         assert_eq!(
             factory.take(),
             factory.clone().take()
         );
     }
 
-    fn create<'r, T: ToMetaFactory>(source: T) -> Box<MetaFactory + 'r> {
+    fn create<Args, T: ToMetaFactory<Args>>(source: T) -> Box<dyn MetaFactory> {
         source.to_metafactory()
     }
 }
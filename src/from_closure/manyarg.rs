@@ -1,15 +1,16 @@
 //! This implements metafactory construction from a closure.
 //!
-//! Using some macro magic, it supports up to 12 arguments.
+//! Using some macro magic, it supports up to 12 arguments. Closures that
+//! need more can take a single argument from the `dependencies` module.
 //!
 //! ```
-//! use metafactory::metafactory;
+//! use metafactory::{ metafactory, cloneable };
 //! use metafactory::AsFactoryExt;
 //!
 //! fn main() {
 //!     // build a metafactory from multi-argument closure.
 //!     let meta_factory = metafactory(
-//!         |a: int, b: bool, c: &'static str| {
+//!         |a: i32, b: bool, c: &'static str| {
 //!             format!("invoked with {}, {}, {}", a, b, c)
 //!         }
 //!     );
@@ -17,9 +18,9 @@
 //!     // create a factory instance this closure.
 //!     // argument factories can be constructed from cloneable sources.
 //!     let factory = meta_factory.new(vec![
-//!         metafactory(3i).new(Vec::new()).ok().unwrap(),
-//!         metafactory(false).new(Vec::new()).ok().unwrap(),
-//!         metafactory("hello").new(Vec::new()).ok().unwrap(),
+//!         metafactory(cloneable(3)).new(Vec::new()).ok().unwrap(),
+//!         metafactory(cloneable(false)).new(Vec::new()).ok().unwrap(),
+//!         metafactory(cloneable("hello")).new(Vec::new()).ok().unwrap(),
 //!     ]).ok().unwrap().as_factory_of::<String>().unwrap();
 //!
 //!     // value should match what factory produced.
@@ -27,83 +28,58 @@
 //! }
 //! ```
 
-use std::any::Any;
+use std::marker::PhantomData;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::ops::DerefMut;
 
 use typedef::TypeDef;
 
-use { MetaFactory, ToMetaFactory, AsFactoryExt };
-use { Factory, Getter };
-use error::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch };
-use aggregate::Aggregate;
+use crate::{ MetaFactory, ToMetaFactory, ArgDescriptor, AsFactoryExt };
+use crate::{ AnyFactory, Factory, Getter, FactoryKind, FactoryType };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, MissingArgument };
+use crate::aggregate::Aggregate;
 
-#[macro_escape]
+#[macro_use]
 mod macros {
-    macro_rules! assert_arg_count(
-        ($expected:expr, $specified:expr)
+    macro_rules! many_arg_closure_impl(
+        ($ClosureScope:ident, $GetterScope:ident: $($_A:ident,$_AT:ty,$_a:ident)|+)
         =>
         (
-            if $expected != $specified {
-                return Err(
-                    FactoryErrorKind::ArgCountMismatch(
-                        ArgCountMismatch::new($expected, $specified)
-                    )
-                )
+            /// Shared closure reference, used as `MetaFactory`.
+            struct $ClosureScope<F, $($_A), +, T> {
+                closure: Rc<RefCell<F>>,
+                _marker: PhantomData<fn($($_AT), +) -> T>,
             }
-        )
-    );
 
-    macro_rules! try_unwrap_factory(
-        ($T:ty, $factory:expr, $index:ident)
-        =>
-        (
-            match $factory.as_factory_of::<$T>() {
-                Some(factory) => factory,
-                None => {
-                    return Err(
-                        FactoryErrorKind::ArgTypeMismatch(
-                            ArgTypeMismatch::new(TypeDef::of::<$T>(), $index)
-                        )
-                    );
-                }
-            }
-        )
-    );
-
-    macro_rules! count_exprs {
-        () => (0);
-        ($head:expr $(, $tail:expr)*) => (1 + count_exprs!($($tail),*));
-    }
-
-    macro_rules! many_arg_closure_impl(
-        ($GetterScope:ident: $($_A:ident,$_AT:ty,$_a:ident)|+)
-        =>
-        (
             /// Contains references to parent factories, so they
             /// can be invoked directly to get arguments for the closure.
-            struct $GetterScope<$($_A:'static), +, T:'static> {
+            struct $GetterScope<F, $($_A:'static), +, T:'static> {
                 // References to argument factories.
                 $(
                     $_a: Factory<$_AT>,
                 )+
                 // Closure reference.
-                closure: Rc<RefCell<|$($_AT), +|:'static -> T>>,
+                closure: Rc<RefCell<F>>,
+                _marker: PhantomData<fn() -> T>,
             }
 
             /// Implement `ToMetaFactory` conversion for closures
-            /// |A1, A2, ... AN| -> T
-            impl<$($_A:'static), +, T:'static> ToMetaFactory for (|$($_AT), +|:'static -> T) {
-                fn to_metafactory<'a>(self) -> Box<MetaFactory + 'a> {
-                    box Rc::new(RefCell::new(self))
+            /// FnMut(A1, A2, ... AN) -> T
+            impl<F, $($_A:'static), +, T:'static> ToMetaFactory<fn($($_AT), +) -> T> for F
+                where F: FnMut($($_AT), +) -> T + 'static
+            {
+                fn to_metafactory(self) -> Box<dyn MetaFactory> {
+                    Box::new($ClosureScope::<F, $($_AT), +, T> {
+                        closure: Rc::new(RefCell::new(self)),
+                        _marker: PhantomData,
+                    })
                 }
             }
 
-            /// Use the closure reference itself as metafactory. For now I did
-            /// not notice any issues with this, but the Rc can be put into some
-            /// wrapper struct, and then we could implement MetaFactory for that.
-            impl<$($_A:'static), +, T:'static> MetaFactory for Rc<RefCell<|$($_AT), +|:'static -> T>> {
+            /// Use the closure reference as metafactory.
+            impl<F, $($_A:'static), +, T:'static> MetaFactory for $ClosureScope<F, $($_AT), +, T>
+                where F: FnMut($($_AT), +) -> T + 'static
+            {
                 fn get_type(&self) -> TypeDef {
                     TypeDef::of::<T>()
                 }
@@ -113,20 +89,28 @@ mod macros {
                     vec![$(TypeDef::of::<$_AT>()), +]
                 }
 
+                fn get_factory_type(&self) -> FactoryType {
+                    FactoryType::of::<T>()
+                }
+
+                fn get_args(&self) -> Vec<ArgDescriptor> {
+                    vec![$(ArgDescriptor::of::<$_AT>()), +]
+                }
+
                 #[allow(unused_assignments)]
-                fn new(&self, arg_getters: Vec<Box<Any>>) -> Result<Box<Any>, FactoryErrorKind> {
+                fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
                     // Calculate required argument count from specified type count.
-                    let required_argc = count_exprs!($($_A),+);
+                    let required_argc = count_exprs!($($_a),+);
 
                     // Return error if count does not match.
                     assert_arg_count!(required_argc, arg_getters.len());
 
-                    let mut getters = arg_getters;
+                    let mut getters = arg_getters.into_iter();
                     let mut arg_index = 0;
                     $(
                         let $_a; // Factory object of correct type.
                         { // Scope so we can reuse `maybe_factory`.
-                            let maybe_factory = getters.remove(0);
+                            let maybe_factory = getters.next().unwrap();
 
                             // Return error if factory does not have a correct type.
                             $_a = try_unwrap_factory!($_AT, maybe_factory, arg_index);
@@ -137,14 +121,15 @@ mod macros {
 
                     // Build factory instance for THIS closure copy, passing
                     // all parent factories as arguments.
-                    let factory = box Factory::<T>::new(
-                        box $GetterScope::<$($_AT), +, T> {
+                    let factory = AnyFactory::from(Factory::<T>::new(
+                        Box::new($GetterScope::<F, $($_AT), +, T> {
                             $(
                                 $_a: $_a,
                             )+
-                            closure: self.clone(),
-                        }
-                    ) as Box<Any>;
+                            closure: self.closure.clone(),
+                            _marker: PhantomData,
+                        })
+                    ));
 
                     Ok(factory)
                 }
@@ -157,26 +142,30 @@ mod macros {
             /// Use GetterScope as a value getter. This is part
             /// that is actually used at runtime, and would benefit from
             /// any further optimizations.
-            impl<'a, $($_A:'static), +, T: 'static> Getter<T> for $GetterScope<$($_AT), +, T> {
+            impl<F, $($_A:'static), +, T: 'static> Getter<T> for $GetterScope<F, $($_AT), +, T>
+                where F: FnMut($($_AT), +) -> T + 'static
+            {
                 fn take(&self) -> T {
-                    (*(self.closure.borrow_mut().deref_mut()))(
-                        $(
-                            self.$_a.take()
-                        ),+
-                    )
+                    // Arguments are produced before the closure is borrowed,
+                    // because the same closure may be invoked by them.
+                    $(
+                        let $_a = self.$_a.take();
+                    )+
+                    (*self.closure.borrow_mut())($($_a),+)
                 }
 
-                fn boxed_clone(&self) -> Box<Getter<T> + 'static> {
+                fn boxed_clone(&self) -> Box<dyn Getter<T>> {
                     $(
                         let $_a = &self.$_a;
                     )+
-                    box $GetterScope::<$($_AT), +, T> {
+                    Box::new($GetterScope::<F, $($_AT), +, T> {
                         $(
                             $_a: $_a.clone()
                         ),
                         +,
                         closure: self.closure.clone(),
-                    }
+                        _marker: PhantomData,
+                    })
                 }
             }
         )
@@ -184,25 +173,25 @@ mod macros {
 }
 
 many_arg_closure_impl!(
-    GetterScope:
+    ClosureScope, GetterScope:
     A, A, a
 );
 
 many_arg_closure_impl!(
-    GetterScope2:
+    ClosureScope2, GetterScope2:
     A1, A1, a1 |
     A2, A2, a2
 );
 
 many_arg_closure_impl!(
-    GetterScope3:
+    ClosureScope3, GetterScope3:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3
 );
 
 many_arg_closure_impl!(
-    GetterScope4:
+    ClosureScope4, GetterScope4:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -210,7 +199,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope5:
+    ClosureScope5, GetterScope5:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -219,7 +208,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope6:
+    ClosureScope6, GetterScope6:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -229,7 +218,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope7:
+    ClosureScope7, GetterScope7:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -240,7 +229,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope8:
+    ClosureScope8, GetterScope8:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -252,7 +241,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope9:
+    ClosureScope9, GetterScope9:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -265,7 +254,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope10:
+    ClosureScope10, GetterScope10:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -279,7 +268,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope11:
+    ClosureScope11, GetterScope11:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -294,7 +283,7 @@ many_arg_closure_impl!(
 );
 
 many_arg_closure_impl!(
-    GetterScope12:
+    ClosureScope12, GetterScope12:
     A1, A1, a1 |
     A2, A2, a2 |
     A3, A3, a3 |
@@ -311,23 +300,22 @@ many_arg_closure_impl!(
 
 #[cfg(test)]
 mod test {
-    use std::any::Any;
     use typedef::TypeDef;
-    use super::super::super::{ ToMetaFactory, MetaFactory, AsFactoryExt }; // super
-    use super::super::super::error::{ FactoryErrorKind }; // really super
+    use crate::{ ToMetaFactory, AnyFactory, FactoryKind, FactoryType, AsFactoryExt, cloneable };
+    use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch, ArgKindMismatch };
 
     #[test]
     fn should_work_with_1_arg_closure() {
         assert_eq!(
             create(
                 |ok: bool| {
-                    if ok { 5i } else { 6i }
+                    if ok { 5i32 } else { 6i32 }
                 },
                 vec![
                     arg(false)
                 ]
-            ).as_factory_of::<int>().unwrap().take(),
-            6i
+            ).as_factory_of::<i32>().unwrap().take(),
+            6i32
         );
     }
 
@@ -336,13 +324,13 @@ mod test {
         assert_eq!(
             create(
                 |a: u8, b: u8| {
-                    (a + b) as uint
+                    (a + b) as usize
                 },
                 vec![
                     arg(13u8), arg(12u8)
                 ]
-            ).as_factory_of::<uint>().unwrap().take(),
-            13u + 12u
+            ).as_factory_of::<usize>().unwrap().take(),
+            13usize + 12usize
         );
     }
 
@@ -377,6 +365,7 @@ mod test {
             Err(FactoryErrorKind::ArgTypeMismatch(e)) => {
                 assert_eq!(e.expected_type, TypeDef::of::<i8>());
                 assert_eq!(e.argument_index, 0);
+                assert_eq!(e.actual_type, TypeDef::of::<bool>());
             },
             _ => panic!("Expected ArgTypeMismatch error!"),
         }
@@ -407,12 +396,13 @@ mod test {
                 a + b + c
             },
             vec![
-            arg(1i8), arg(1i8), arg(box 23.3f64)
+            arg(1i8), arg(1i8), arg(Box::new(23.3f64))
             ]
         ) {
             Err(FactoryErrorKind::ArgTypeMismatch(e)) => {
                 assert_eq!(e.expected_type, TypeDef::of::<i8>());
                 assert_eq!(e.argument_index, 2);
+                assert_eq!(e.actual_type, TypeDef::of::<Box<f64>>());
             },
             _ => panic!("Expected ArgTypeMismatch error!"),
         }
@@ -425,7 +415,7 @@ mod test {
                 a + b + c
             },
             vec![
-            arg(1i8), arg(false), arg(box 23.3f64)
+            arg(1i8), arg(false), arg(Box::new(23.3f64))
             ]
         ) {
             Err(FactoryErrorKind::ArgTypeMismatch(e)) => {
@@ -436,6 +426,96 @@ mod test {
         }
     }
 
+    #[test]
+    fn should_check_args_without_factories() {
+        let meta_sum = crate::metafactory(|a: i8, b: bool| if b { a } else { -a });
+
+        assert!(meta_sum.check_args(&[FactoryType::of::<i8>(), FactoryType::of::<bool>()]).is_ok());
+        assert_eq!(
+            meta_sum.check_args(&[FactoryType::of::<i8>()]),
+            Err(FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 1)))
+        );
+        assert_eq!(
+            meta_sum.check_args(&[FactoryType::of::<i8>(), FactoryType::of::<f64>()]),
+            Err(FactoryErrorKind::ArgTypeMismatch(
+                ArgTypeMismatch::new(TypeDef::of::<bool>(), 1, TypeDef::of::<f64>())
+            ))
+        );
+        assert_eq!(
+            meta_sum.check_args(&[FactoryType::of::<i8>(), FactoryType::of::<f64>()]).err().unwrap().to_string(),
+            "argument 1 expected bool, got f64"
+        );
+    }
+
+    #[test]
+    fn should_reject_fallible_factory_for_plain_arg() {
+        let meta_double = crate::metafactory(|a: i32| a * 2);
+        let try_arg = crate::fallible::try_metafactory(|| -> Result<i32, String> { Ok(1) })
+            .new(Vec::new()).ok().unwrap();
+        let expected = FactoryErrorKind::ArgKindMismatch(ArgKindMismatch::new(
+            TypeDef::of::<i32>(), 0, FactoryKind::Plain, FactoryKind::fallible::<String>()
+        ));
+
+        assert_eq!(meta_double.check_args(&[try_arg.get_factory_type()]), Err(expected.clone()));
+
+        let error = meta_double.new(vec![try_arg]).err().unwrap();
+        assert_eq!(error, expected);
+        assert_eq!(error.to_string(), "argument 0 expected Factory<i32>, got TryFactory<i32, alloc::string::String>");
+    }
+
+    #[test]
+    fn should_reject_send_factory_for_plain_arg() {
+        let meta_double = crate::metafactory(|a: i32| a * 2);
+        let send_arg = crate::sync::argless_as_send_factory(cloneable(1i32));
+        let expected = FactoryErrorKind::ArgKindMismatch(
+            ArgKindMismatch::new(TypeDef::of::<i32>(), 0, FactoryKind::Plain, FactoryKind::Send)
+        );
+
+        assert_eq!(meta_double.check_args(&[send_arg.get_factory_type()]), Err(expected.clone()));
+
+        let error = meta_double.new(vec![send_arg]).err().unwrap();
+        assert_eq!(error, expected);
+        assert_eq!(error.to_string(), "argument 0 expected Factory<i32>, got SendFactory<i32>");
+    }
+
+    #[test]
+    fn should_validate_all_bad_arg_types() {
+        let meta_sum = crate::metafactory(|a: i8, b: i8, c: i8| a + b + c);
+
+        match meta_sum.validate(&[FactoryType::of::<i8>(), FactoryType::of::<bool>(), FactoryType::of::<f64>()]) {
+            Err(FactoryErrorKind::Multiple(errors)) => {
+                assert_eq!(errors, vec![
+                    FactoryErrorKind::ArgTypeMismatch(
+                        ArgTypeMismatch::new(TypeDef::of::<i8>(), 1, TypeDef::of::<bool>())
+                    ),
+                    FactoryErrorKind::ArgTypeMismatch(
+                        ArgTypeMismatch::new(TypeDef::of::<i8>(), 2, TypeDef::of::<f64>())
+                    ),
+                ]);
+            },
+            _ => panic!("Expected Multiple error!"),
+        }
+    }
+
+    #[test]
+    fn should_validate_arg_count_and_types_together() {
+        let meta_sum = crate::metafactory(|a: i8, b: i8, c: i8| a + b + c);
+
+        assert!(meta_sum.validate(&[FactoryType::of::<i8>(), FactoryType::of::<i8>(), FactoryType::of::<i8>()]).is_ok());
+
+        match meta_sum.validate(&[FactoryType::of::<bool>()]) {
+            Err(FactoryErrorKind::Multiple(errors)) => {
+                assert_eq!(errors, vec![
+                    FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(3, 1)),
+                    FactoryErrorKind::ArgTypeMismatch(
+                        ArgTypeMismatch::new(TypeDef::of::<i8>(), 0, TypeDef::of::<bool>())
+                    ),
+                ]);
+            },
+            _ => panic!("Expected Multiple error!"),
+        }
+    }
+
     #[test]
     fn should_work_with_12_arg_closure() {
         assert_eq!(
@@ -455,15 +535,53 @@ mod test {
         );
     }
 
-    fn create<T: ToMetaFactory>(source: T, args: Vec<Box<Any>>) -> Box<Any> {
+    #[test]
+    fn should_allow_same_closure_in_its_own_arguments() {
+        let meta_sum = crate::metafactory(|a: i32, b: i32| a + b);
+
+        let sum = meta_sum.new(vec![
+            meta_sum.new(vec![arg(1i32), arg(2i32)]).ok().unwrap(),
+            arg(3i32),
+        ]).ok().unwrap().as_factory_of::<i32>().unwrap();
+
+        assert_eq!(sum.take(), 6);
+    }
+
+    #[test]
+    fn should_call_mutable_closure() {
+        let mut calls = 0u32;
+        let factory = create(
+            move |step: u32| { calls += step; calls },
+            vec![arg(2u32)]
+        ).as_factory_of::<u32>().unwrap();
+
+        assert_eq!(factory.take(), 2);
+        assert_eq!(factory.take(), 4);
+    }
+
+    #[test]
+    fn should_require_option_argument() {
+        let meta = (|a: Option<i32>| a.unwrap_or(0)).to_metafactory();
+
+        assert!(!meta.get_args()[0].optional);
+        assert_eq!(meta.get_arg_types(), vec![TypeDef::of::<Option<i32>>()]);
+        assert!(meta.new(vec![AnyFactory::empty()]).is_err());
+        assert!(meta.new(Vec::new()).is_err());
+
+        let factory = meta.new(vec![arg(Some(3i32))]).ok().unwrap()
+            .as_factory_of::<i32>().unwrap();
+        assert_eq!(factory.take(), 3);
+    }
+
+    fn create<Args, T: ToMetaFactory<Args>>(source: T, args: Vec<AnyFactory>) -> AnyFactory {
         source.to_metafactory().new(args).ok().unwrap()
     }
 
-    fn maybe_create<T: ToMetaFactory>(source: T, args: Vec<Box<Any>>) -> Result<Box<Any>, FactoryErrorKind>  {
+    fn maybe_create<Args, T: ToMetaFactory<Args>>(source: T, args: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind>  {
         source.to_metafactory().new(args)
     }
 
-    fn arg<T: ToMetaFactory>(source: T) -> Box<Any> {
-        source.to_metafactory().new(Vec::new()).ok().unwrap()
+    fn arg<T: Clone + 'static>(source: T) -> AnyFactory {
+        cloneable(source).to_metafactory().new(Vec::new()).ok().unwrap()
     }
 }
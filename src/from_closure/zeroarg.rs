@@ -21,33 +21,53 @@
 //! }
 //! ```
 
-use std::any::Any;
+use std::marker::PhantomData;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::ops::DerefMut;
 
 use typedef::TypeDef;
 
-use { MetaFactory, ToMetaFactory };
-use { Factory, Getter };
-use error::{ FactoryErrorKind };
-use aggregate::Aggregate;
+use crate::{ MetaFactory, ToMetaFactory };
+use crate::{ AnyFactory, Factory, FactoryType, Getter };
+use crate::error::FactoryErrorKind;
+use crate::aggregate::Aggregate;
+
+/// Shared closure reference, used both as `MetaFactory` and as `Getter`.
+struct ClosureScope<F, T> {
+    closure: Rc<RefCell<F>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<F, T> Clone for ClosureScope<F, T> {
+    fn clone(&self) -> ClosureScope<F, T> {
+        ClosureScope {
+            closure: self.closure.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
 
 /// Creates `MetaFactory` from closure function.
-#[stable]
-impl<T:'static> ToMetaFactory for (||:'static -> T) {
-    fn to_metafactory<'a>(self) -> Box<MetaFactory + 'a> {
+impl<F, T> ToMetaFactory<fn() -> T> for F
+    where F: FnMut() -> T + 'static, T: 'static
+{
+    fn to_metafactory(self) -> Box<dyn MetaFactory> {
         // We have only one closure, but the meta factory
         // will need to create many factories. There is no way around it
         // but put this single closure into reference-counted cell
         // so it can be uniquely dereferenced and called when each cloned Rc
         // is invoked in factory.
-        box Rc::new(RefCell::new(self))
+        Box::new(ClosureScope::<F, T> {
+            closure: Rc::new(RefCell::new(self)),
+            _marker: PhantomData,
+        })
     }
 }
 
 /// Use closure itself as `MetaFactory`.
-impl<T:'static> MetaFactory for Rc<RefCell<||:'static -> T>> {
+impl<F, T> MetaFactory for ClosureScope<F, T>
+    where F: FnMut() -> T + 'static, T: 'static
+{
     fn get_type(&self) -> TypeDef {
         TypeDef::of::<T>()
     }
@@ -56,11 +76,15 @@ impl<T:'static> MetaFactory for Rc<RefCell<||:'static -> T>> {
         Vec::new()
     }
 
-    fn new(&self, _arg_getters: Vec<Box<Any>>) -> Result<Box<Any>, FactoryErrorKind> {
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::of::<T>()
+    }
+
+    fn new(&self, _arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
         Ok(
-            box Factory::<T>::new(
-                box self.clone()
-            ) as Box<Any>
+            AnyFactory::from(Factory::<T>::new(
+                Box::new(self.clone())
+            ))
         )
     }
 
@@ -70,26 +94,28 @@ impl<T:'static> MetaFactory for Rc<RefCell<||:'static -> T>> {
 }
 
 /// And also use closure itself as created `Factory`.
-impl<T: 'static> Getter<T> for Rc<RefCell<||:'static -> T>> {
+impl<F, T> Getter<T> for ClosureScope<F, T>
+    where F: FnMut() -> T + 'static, T: 'static
+{
     fn take(&self) -> T {
-        (*(self.borrow_mut().deref_mut()))()
+        (*self.closure.borrow_mut())()
     }
 
-    fn boxed_clone(&self) -> Box<Getter<T> + 'static> {
-        box self.clone()
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(self.clone())
     }
 }
 
 #[cfg(test)]
 mod test {
     use typedef::TypeDef;
-    use super::super::super::{ ToMetaFactory, MetaFactory, AsFactoryExt }; // super
+    use crate::{ ToMetaFactory, MetaFactory, FactoryType, AsFactoryExt };
 
     #[test]
     fn should_return_correct_type() {
         assert_eq!(
-            create(|| 24i).get_type(),
-            TypeDef::of::<int>()
+            create(|| 24i32).get_type(),
+            TypeDef::of::<i32>()
         );
         assert_eq!(
             create(|| 1f32).get_type(),
@@ -100,7 +126,7 @@ mod test {
             TypeDef::of::<String>()
         );
         assert_eq!(
-            create(|| box "aaa".to_string()).get_type(),
+            create(|| Box::new("aaa".to_string())).get_type(),
             TypeDef::of::<Box<String>>()
         );
     }
@@ -108,29 +134,42 @@ mod test {
     #[test]
     fn should_require_no_arguments() {
         assert_eq!(
-            create(|| 24i).get_arg_types().len(),
+            create(|| 24i32).get_arg_types().len(),
             0
         );
+        assert!(create(|| 24i32).check_args(&[]).is_ok());
+        assert!(create(|| 24i32).check_args(&[FactoryType::of::<i32>()]).is_err());
     }
 
     #[test]
     fn should_build_usable_factory() {
         assert_eq!(
-            create(|| 24i).new(Vec::new()).ok().unwrap().as_factory_of::<int>().unwrap().take(),
-            24i
+            create(|| 24i32).new(Vec::new()).ok().unwrap().as_factory_of::<i32>().unwrap().take(),
+            24i32
         );
     }
 
     #[test]
     fn factory_clone_should_return_same_value() {
-        let factory = create(|| 24i).new(Vec::new()).ok().unwrap().as_factory_of::<int>().unwrap();
+        let factory = create(|| 24i32).new(Vec::new()).ok().unwrap().as_factory_of::<i32>().unwrap();
         assert_eq!(
             factory.take(),
             factory.clone().take()
         );
     }
 
-    fn create<'r, T: ToMetaFactory>(source: T) -> Box<MetaFactory + 'r> {
+    #[test]
+    fn should_call_mutable_closure() {
+        let mut counter = 0i32;
+        let factory = create(move || { counter += 1; counter })
+            .new(Vec::new()).ok().unwrap()
+            .as_factory_of::<i32>().unwrap();
+
+        assert_eq!(factory.take(), 1);
+        assert_eq!(factory.clone().take(), 2);
+    }
+
+    fn create<Args, T: ToMetaFactory<Args>>(source: T) -> Box<dyn MetaFactory> {
         source.to_metafactory()
     }
 }
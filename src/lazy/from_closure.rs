@@ -0,0 +1,280 @@
+//! This implements lazy argument metafactory construction from a closure.
+//!
+//! Supports closures with up to 12 arguments, same as regular closures.
+
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, ArgDescriptor, Factory, Getter, AsFactoryExt, FactoryKind, FactoryType };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, MissingArgument };
+use crate::aggregate::Aggregate;
+use super::{ ToLazyMetaFactory, LazyArgument };
+
+#[macro_use]
+mod macros {
+    macro_rules! lazy_closure_impl(
+        ($ClosureScope:ident, $GetterScope:ident: $($_P:ident,$_a:ident)|+)
+        =>
+        (
+            /// Shared closure reference, used as `MetaFactory`.
+            struct $ClosureScope<F, $($_P,)+ T> {
+                closure: Rc<F>,
+                _marker: PhantomData<fn($($_P),+) -> T>,
+            }
+
+            /// Contains references to parent factories, which are passed
+            /// to the closure without taking values from them.
+            struct $GetterScope<F, $($_P: LazyArgument,)+ T: 'static> {
+                // References to argument factories, `None` for empty
+                // optional slots.
+                $(
+                    $_a: Option<Factory<$_P::Value>>,
+                )+
+                // Closure reference.
+                closure: Rc<F>,
+                _marker: PhantomData<fn() -> T>,
+            }
+
+            /// Implement `ToLazyMetaFactory` conversion for closures
+            /// Fn(P1, P2, ... PN) -> T
+            impl<F, $($_P: LazyArgument,)+ T: 'static> ToLazyMetaFactory<fn($($_P),+) -> T> for F
+                where F: Fn($($_P),+) -> T + 'static
+            {
+                fn to_lazy_metafactory(self) -> Box<dyn MetaFactory> {
+                    Box::new($ClosureScope::<F, $($_P,)+ T> {
+                        closure: Rc::new(self),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+
+            /// Use the closure reference as metafactory.
+            impl<F, $($_P: LazyArgument,)+ T: 'static> MetaFactory for $ClosureScope<F, $($_P,)+ T>
+                where F: Fn($($_P),+) -> T + 'static
+            {
+                fn get_type(&self) -> TypeDef {
+                    TypeDef::of::<T>()
+                }
+
+                fn get_arg_types(&self) -> Vec<TypeDef> {
+                    // Report wrapped value types, not the parameter types.
+                    vec![$(TypeDef::of::<$_P::Value>()),+]
+                }
+
+                fn get_factory_type(&self) -> FactoryType {
+                    FactoryType::of::<T>()
+                }
+
+                fn get_args(&self) -> Vec<ArgDescriptor> {
+                    vec![$(
+                        ArgDescriptor {
+                            optional: $_P::OPTIONAL,
+                            ..ArgDescriptor::of::<$_P::Value>()
+                        }
+                    ),+]
+                }
+
+                #[allow(unused_assignments)]
+                fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+                    check_arg_count(&self.get_args(), arg_getters.len())?;
+
+                    let mut getters = arg_getters.into_iter();
+                    let mut arg_index = 0;
+                    $(
+                        let $_a;
+                        {
+                            // Empty and omitted slots reach here only
+                            // if the argument is optional.
+                            $_a = match getters.next() {
+                                Some(ref maybe_factory) if maybe_factory.is_empty() && $_P::OPTIONAL => None,
+                                Some(maybe_factory) => Some(try_unwrap_factory!($_P::Value, maybe_factory, arg_index)),
+                                None => None,
+                            };
+
+                            arg_index += 1;
+                        }
+                    )+
+
+                    let factory = AnyFactory::from(Factory::<T>::new(
+                        Box::new($GetterScope::<F, $($_P,)+ T> {
+                            $(
+                                $_a,
+                            )+
+                            closure: self.closure.clone(),
+                            _marker: PhantomData,
+                        })
+                    ));
+
+                    Ok(factory)
+                }
+
+                fn new_aggregate(&self) -> Aggregate<'static> {
+                    Aggregate::new::<T>()
+                }
+            }
+
+            impl<F, $($_P: LazyArgument,)+ T: 'static> Getter<T> for $GetterScope<F, $($_P,)+ T>
+                where F: Fn($($_P),+) -> T + 'static
+            {
+                fn take(&self) -> T {
+                    // Only factory clones are passed, values are produced
+                    // by the closure when it needs them. The closure is not
+                    // borrowed mutably, because they may invoke it again.
+                    $(
+                        let $_a = $_P::from_factory(self.$_a.as_ref());
+                    )+
+                    (self.closure)($($_a),+)
+                }
+
+                fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+                    Box::new($GetterScope::<F, $($_P,)+ T> {
+                        $(
+                            $_a: self.$_a.clone(),
+                        )+
+                        closure: self.closure.clone(),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+        )
+    );
+}
+
+/// Return `ArgCountMismatch` error if required arguments are missing
+/// or there are too many of them.
+fn check_arg_count(args: &[ArgDescriptor], specified: usize) -> Result<(), FactoryErrorKind> {
+    if specified < crate::required_arg_count(args) || specified > args.len() {
+        return Err(FactoryErrorKind::ArgCountMismatch(
+            ArgCountMismatch::new(args.len(), specified)
+        ));
+    }
+    Ok(())
+}
+
+lazy_closure_impl!(
+    ClosureScope1, GetterScope1:
+    P1, a1
+);
+
+lazy_closure_impl!(
+    ClosureScope2, GetterScope2:
+    P1, a1 |
+    P2, a2
+);
+
+lazy_closure_impl!(
+    ClosureScope3, GetterScope3:
+    P1, a1 |
+    P2, a2 |
+    P3, a3
+);
+
+lazy_closure_impl!(
+    ClosureScope4, GetterScope4:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4
+);
+
+lazy_closure_impl!(
+    ClosureScope5, GetterScope5:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5
+);
+
+lazy_closure_impl!(
+    ClosureScope6, GetterScope6:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6
+);
+
+lazy_closure_impl!(
+    ClosureScope7, GetterScope7:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7
+);
+
+lazy_closure_impl!(
+    ClosureScope8, GetterScope8:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7 |
+    P8, a8
+);
+
+lazy_closure_impl!(
+    ClosureScope9, GetterScope9:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7 |
+    P8, a8 |
+    P9, a9
+);
+
+lazy_closure_impl!(
+    ClosureScope10, GetterScope10:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7 |
+    P8, a8 |
+    P9, a9 |
+    P10, a10
+);
+
+lazy_closure_impl!(
+    ClosureScope11, GetterScope11:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7 |
+    P8, a8 |
+    P9, a9 |
+    P10, a10 |
+    P11, a11
+);
+
+lazy_closure_impl!(
+    ClosureScope12, GetterScope12:
+    P1, a1 |
+    P2, a2 |
+    P3, a3 |
+    P4, a4 |
+    P5, a5 |
+    P6, a6 |
+    P7, a7 |
+    P8, a8 |
+    P9, a9 |
+    P10, a10 |
+    P11, a11 |
+    P12, a12
+);
@@ -0,0 +1,266 @@
+//! Implements metafactories with lazily evaluated arguments.
+//!
+//! Closure metafactories take a value from every argument factory before
+//! invoking the closure. Closures converted with `lazy_metafactory`
+//! receive `Lazy<A>` or `Factory<A>` parameters instead, and produce the
+//! argument value only if they ask for it.
+//!
+//! The metafactory still reports `A` as the argument type, so it is wired
+//! exactly the same way as a closure that takes `A`.
+//!
+//! A parameter of type `Option<A>` declares an optional dependency. Its
+//! value is taken eagerly, but the argument slot may be left empty with
+//! `AnyFactory::empty`, or omitted if it is at the end of argument list,
+//! and the closure then receives `None`. `get_args` marks such arguments
+//! as optional.
+//!
+//! Lazy closures must be `Fn`: arguments are produced while the closure
+//! runs, and they may invoke the same closure again.
+//!
+//! Only lazy metafactories have optional arguments. Closures converted
+//! with `metafactory` treat `Option<A>` as a required argument of type
+//! `Option<A>`.
+//!
+//! ```
+//! use typedef::TypeDef;
+//! use metafactory::{ metafactory, Factory, AsFactoryExt };
+//! use metafactory::lazy::{ lazy_metafactory, Lazy };
+//!
+//! fn main() {
+//!     let meta_pick = lazy_metafactory(|use_cache: Lazy<bool>, cached: Lazy<i32>, fresh: Factory<i32>| {
+//!         if *use_cache.get() { *cached.get() } else { fresh.take() }
+//!     });
+//!
+//!     assert_eq!(
+//!         meta_pick.get_arg_types(),
+//!         vec![TypeDef::of::<bool>(), TypeDef::of::<i32>(), TypeDef::of::<i32>()]
+//!     );
+//!
+//!     let pick = meta_pick.new(vec![
+//!         metafactory(|| false).new(Vec::new()).ok().unwrap(),
+//!         metafactory(|| -> i32 { panic!("not needed") }).new(Vec::new()).ok().unwrap(),
+//!         metafactory(|| 7i32).new(Vec::new()).ok().unwrap(),
+//!     ]).ok().unwrap().as_factory_of::<i32>().unwrap();
+//!
+//!     assert_eq!(pick.take(), 7);
+//! }
+//! ```
+
+use std::cell::OnceCell;
+
+use crate::{ MetaFactory, Factory };
+
+mod from_closure;
+
+/// Argument value that is produced on first access.
+pub struct Lazy<A: 'static> {
+    factory: Factory<A>,
+    value: OnceCell<A>,
+}
+
+impl<A: 'static> Lazy<A> {
+    /// Create a new `Lazy` value that will be taken from the factory.
+    pub fn new(factory: Factory<A>) -> Lazy<A> {
+        Lazy {
+            factory,
+            value: OnceCell::new(),
+        }
+    }
+
+    /// Get the value, producing it on the first call.
+    pub fn get(&self) -> &A {
+        self.value.get_or_init(|| self.factory.take())
+    }
+
+    /// Get the owned value, producing it if it was not produced yet.
+    pub fn into_inner(self) -> A {
+        let factory = self.factory;
+        self.value.into_inner().unwrap_or_else(|| factory.take())
+    }
+
+    /// Check if the value was already produced.
+    pub fn is_evaluated(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+/// Closure parameter type that is created from argument factory
+/// by the closure getter.
+pub trait LazyArgument: 'static {
+    /// Type of the argument value, reported by `get_arg_types`.
+    type Value: 'static;
+
+    /// Argument slot may be empty.
+    const OPTIONAL: bool = false;
+
+    /// Create parameter from the argument factory.
+    ///
+    /// Factory is `None` only if the argument is optional.
+    fn from_factory(factory: Option<&Factory<Self::Value>>) -> Self;
+}
+
+impl<A: 'static> LazyArgument for Lazy<A> {
+    type Value = A;
+
+    fn from_factory(factory: Option<&Factory<A>>) -> Lazy<A> {
+        Lazy::new(factory.unwrap().clone())
+    }
+}
+
+impl<A: 'static> LazyArgument for Factory<A> {
+    type Value = A;
+
+    fn from_factory(factory: Option<&Factory<A>>) -> Factory<A> {
+        factory.unwrap().clone()
+    }
+}
+
+impl<A: 'static> LazyArgument for Option<A> {
+    type Value = A;
+
+    const OPTIONAL: bool = true;
+
+    fn from_factory(factory: Option<&Factory<A>>) -> Option<A> {
+        factory.map(|f| f.take())
+    }
+}
+
+/// Trait for closures convertable to `MetaFactory` with lazy arguments.
+pub trait ToLazyMetaFactory<Args> {
+    /// Creates a `MetaFactory` that passes `LazyArgument` parameters
+    /// to the closure.
+    fn to_lazy_metafactory(self) -> Box<dyn MetaFactory>;
+}
+
+/// Create a new `MetaFactory` from a closure whose parameters are
+/// `Lazy<A>`, `Factory<A>` or `Option<A>`.
+pub fn lazy_metafactory<Args, T: ToLazyMetaFactory<Args>>(any: T) -> Box<dyn MetaFactory> {
+    any.to_lazy_metafactory()
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use typedef::TypeDef;
+    use crate::{ argless_as_factory, cloneable, Factory, AnyFactory, ArgDescriptor, FactoryType, AsFactoryExt };
+    use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch, MissingArgument };
+    use super::{ lazy_metafactory, Lazy };
+
+    #[test]
+    fn should_report_value_types_as_arguments() {
+        let meta = lazy_metafactory(|a: Lazy<i32>, b: Factory<bool>| if b.take() { *a.get() } else { 0 });
+
+        assert_eq!(meta.get_type(), TypeDef::of::<i32>());
+        assert_eq!(meta.get_arg_types(), vec![TypeDef::of::<i32>(), TypeDef::of::<bool>()]);
+        assert!(meta.check_args(&[FactoryType::of::<i32>(), FactoryType::of::<bool>()]).is_ok());
+    }
+
+    #[test]
+    fn should_not_produce_unused_argument() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = {
+            let calls = calls.clone();
+            argless_as_factory(move || { calls.set(calls.get() + 1); 5i32 })
+        };
+
+        let factory = lazy_metafactory(|flag: Lazy<bool>, value: Lazy<i32>| {
+            if *flag.get() { value.into_inner() } else { -1 }
+        }).new(vec![argless_as_factory(cloneable(false)), counted]).ok().unwrap()
+            .as_factory_of::<i32>().unwrap();
+
+        assert_eq!(factory.take(), -1);
+        assert_eq!(factory.clone().take(), -1);
+        assert_eq!(calls.get(), 0);
+    }
+
+    #[test]
+    fn should_produce_lazy_value_once() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = {
+            let calls = calls.clone();
+            argless_as_factory(move || { calls.set(calls.get() + 1); calls.get() })
+        };
+
+        let factory = lazy_metafactory(|value: Lazy<i32>| {
+            assert!(!value.is_evaluated());
+            *value.get() + *value.get()
+        }).new(vec![counted]).ok().unwrap()
+            .as_factory_of::<i32>().unwrap();
+
+        assert_eq!(factory.take(), 2);
+        assert_eq!(factory.take(), 4);
+    }
+
+    #[test]
+    fn should_describe_optional_arguments() {
+        let meta = lazy_metafactory(|a: Lazy<i32>, b: Option<bool>| (*a.get(), b));
+
+        assert_eq!(meta.get_arg_types(), vec![TypeDef::of::<i32>(), TypeDef::of::<bool>()]);
+        assert_eq!(meta.get_args(), vec![
+            ArgDescriptor::required(TypeDef::of::<i32>()),
+            ArgDescriptor::optional(TypeDef::of::<bool>()),
+        ]);
+    }
+
+    #[test]
+    fn should_pass_none_for_empty_or_omitted_optional_argument() {
+        let meta = lazy_metafactory(|a: Lazy<i32>, b: Option<bool>| (*a.get(), b));
+
+        let omitted = meta.new(vec![argless_as_factory(cloneable(1i32))]).ok().unwrap()
+            .as_factory_of::<(i32, Option<bool>)>().unwrap();
+        let empty = meta.new(vec![argless_as_factory(cloneable(2i32)), AnyFactory::empty()]).ok().unwrap()
+            .as_factory_of::<(i32, Option<bool>)>().unwrap();
+        let specified = meta.new(vec![argless_as_factory(cloneable(3i32)), argless_as_factory(cloneable(true))]).ok().unwrap()
+            .as_factory_of::<(i32, Option<bool>)>().unwrap();
+
+        assert_eq!(omitted.take(), (1, None));
+        assert_eq!(empty.take(), (2, None));
+        assert_eq!(specified.take(), (3, Some(true)));
+        assert!(meta.check_args(&[FactoryType::of::<i32>()]).is_ok());
+    }
+
+    #[test]
+    fn should_require_arguments_before_optional_ones() {
+        let meta = lazy_metafactory(|a: Option<i32>, b: Lazy<bool>| (a, *b.get()));
+
+        assert_eq!(
+            meta.new(Vec::new()).err().unwrap(),
+            FactoryErrorKind::ArgCountMismatch(ArgCountMismatch::new(2, 0))
+        );
+        assert_eq!(
+            meta.new(vec![argless_as_factory(cloneable(1i32)), AnyFactory::empty()]).err().unwrap(),
+            FactoryErrorKind::MissingArgument(MissingArgument::new(TypeDef::of::<bool>(), 1))
+        );
+
+        let factory = meta.new(vec![AnyFactory::empty(), argless_as_factory(cloneable(true))]).ok().unwrap()
+            .as_factory_of::<(Option<i32>, bool)>().unwrap();
+        assert_eq!(factory.take(), (None, true));
+    }
+
+    #[test]
+    fn should_allow_same_closure_in_its_own_arguments() {
+        let meta_sum = lazy_metafactory(|a: Lazy<i32>, b: Lazy<i32>| *a.get() + *b.get());
+
+        let inner = meta_sum.new(vec![
+            argless_as_factory(cloneable(1i32)),
+            argless_as_factory(cloneable(2i32)),
+        ]).ok().unwrap();
+        let sum = meta_sum.new(vec![inner, argless_as_factory(cloneable(3i32))]).ok().unwrap()
+            .as_factory_of::<i32>().unwrap();
+
+        assert_eq!(sum.take(), 6);
+    }
+
+    #[test]
+    fn should_return_arg_type_mismatch_for_value_type() {
+        let meta = lazy_metafactory(|a: Lazy<i32>| *a.get());
+
+        match meta.new(vec![argless_as_factory(cloneable(true))]) {
+            Err(e) => assert_eq!(e, FactoryErrorKind::ArgTypeMismatch(
+                ArgTypeMismatch::new(TypeDef::of::<i32>(), 0, TypeDef::of::<bool>())
+            )),
+            _ => panic!("Expected ArgTypeMismatch error!"),
+        }
+    }
+}
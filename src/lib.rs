@@ -13,19 +13,19 @@
 //! Let's look at really small example first:
 //!
 //! ```
-//! use metafactory::{ metafactory, argless_as_factory, AsFactoryExt };
+//! use metafactory::{ metafactory, argless_as_factory, cloneable, AsFactoryExt };
 //!
 //! fn main() {
 //!     let meta_sum = metafactory(
-//!         |a: int, b: int| a + b
+//!         |a: i32, b: i32| a + b
 //!     );
 //!
 //!     let sum_factory = meta_sum.new(vec![
-//!         argless_as_factory(5i),
-//!         argless_as_factory(6i),
+//!         argless_as_factory(cloneable(5)),
+//!         argless_as_factory(cloneable(6)),
 //!     ]).ok().unwrap();
 //!
-//!     let getter = sum_factory.as_factory_of::<int>().unwrap();
+//!     let getter = sum_factory.as_factory_of::<i32>().unwrap();
 //!
 //!     assert_eq!(getter.take(), 11);
 //! }
@@ -37,13 +37,17 @@
 //! It has a method `new`, which is used above to return a real
 //! concrete factory `sum_factory`. As argument, it takes other factories.
 //! The method `argless_as_factory()` returns factories for clonable values
-//! `5i` and `6i`.
+//! `5` and `6`.
 //!
 //! So, metafactories can be created from different sources: clonable
-//! objects or closures. In this case `5i` is a clonable object.
+//! objects or closures. In this case `5` is a clonable object. It is
+//! wrapped with `cloneable`, because a closure can also be clonable, and
+//! we need to tell the two apart.
 //!
-//! Returned `sum_factory` has a `Box<Any>` type, and can be downcasted to
-//! a `Factory` of appropriate type with `as_factory_of` method.
+//! Returned `sum_factory` has an `AnyFactory` type, and can be downcasted to
+//! a `Factory` of appropriate type with `as_factory_of` method. `AnyFactory`
+//! also knows the type of values it produces, so argument type errors can
+//! tell both the expected and the actual type.
 //!
 //! Then you can call `take()` on this factory `getter` to invoke your closure
 //! and return a new value.
@@ -59,30 +63,30 @@
 //! argument, and creates our own struct:
 //!
 //! ```
-//! use metafactory::{ metafactory, argless_as_factory, AsFactoryExt };
+//! use metafactory::{ metafactory, argless_as_factory, cloneable, AsFactoryExt };
 //!
 //! /// Our own struct.
 //! struct Foo {
-//!     value: int,
+//!     value: i32,
 //! }
 //!
 //! fn main() {
 //!     // initialization
 //!
 //!     let meta_sum = metafactory(
-//!         |a: int, b: int| a + b
+//!         |a: i32, b: i32| a + b
 //!     );
 //!
 //!     let meta_foo = metafactory(
-//!         |sum: int| Foo { value: sum }
+//!         |sum: i32| Foo { value: sum }
 //!     );
 //!
 //!     // plugging in
 //!
 //!     let foo_factory = meta_foo.new(vec![
 //!         meta_sum.new(vec![
-//!             argless_as_factory(5i),
-//!             argless_as_factory(6i),
+//!             argless_as_factory(cloneable(5)),
+//!             argless_as_factory(cloneable(6)),
 //!         ]).ok().unwrap()
 //!     ]).ok().unwrap();
 //!
@@ -101,37 +105,41 @@
 //! Created metafactories can also be used to inspect closure argument types.
 //! It is also possible to clone the created factory: in such case, all
 //! the call tree is also cloned internally. This makes it possible to
-//! pass a copy of a factory to a task.
+//! pass a copy of a factory to a task, if it was built with thread-safe
+//! metafactories from the `sync` module.
 //!
-//! Factories intentionally do not produce any kind of singletons or
-//! references, only new values. You can also look at them as configurable
-//! stream of values.
+//! Factories do not produce any kind of singletons or references by
+//! default, only new values. You can also look at them as configurable
+//! stream of values. Memoizing is opt-in: `Factory::cached` and
+//! `Factory::shared` wrap a factory in getters from the `singleton` module,
+//! and `Factory::scoped` binds it to a unit of work from the `scope` module.
 //!
 //! If this library looks a bit lower-level, it is because it is intended as
 //! such: more convenient wrappers like dependency injection or plugin
-//! architecture can be implemented on top of this.
+//! architecture can be implemented on top of this. A simple named
+//! registry is included in the `registry` module.
 //!
 //! Finally, a more complete example of available functionality:
 //!
 //! ```
-//! use metafactory::{ metafactory, AsFactoryExt };
+//! use metafactory::{ metafactory, cloneable, AsFactoryExt };
 //!
 //! fn main() {
 //!     // build argument-factory from cloneable source.
-//!     let meta_arg1 = metafactory(5i);
+//!     let meta_arg1 = metafactory(cloneable(5i64));
 //!
 //!     // build argument-factory from lambda.
 //!     let meta_arg2 = metafactory(|| 14i32);
 //!
 //!     // build a factory that uses other factories create added numbers.
-//!     let meta_adder = metafactory(|a1: int, a2: i32| a1 + a2 as int);
+//!     let meta_adder = metafactory(|a1: i64, a2: i32| a1 + a2 as i64);
 //!
-//!     // it knows the cloneable source returns int
-//!     assert!(meta_arg1.get_type().is::<int>());
+//!     // it knows the cloneable source returns i64
+//!     assert!(meta_arg1.get_type().is::<i64>());
 //!     // it knows the lambda source returns i32
 //!     assert!(meta_arg2.get_type().is::<i32>());
-//!     // it knows the lambda with 2 args returns int
-//!     assert!(meta_adder.get_type().is::<int>());
+//!     // it knows the lambda with 2 args returns i64
+//!     assert!(meta_adder.get_type().is::<i64>());
 //!
 //!     // create a factory for adder, pass other 2 factories as arguments
 //!     let boxany = meta_adder.new(vec![
@@ -140,7 +148,7 @@
 //!     ]).ok().unwrap();
 //!
 //!     // conveniently downcast factory to callable instance
-//!     let factory = boxany.as_factory_of::<int>().unwrap();
+//!     let factory = boxany.as_factory_of::<i64>().unwrap();
 //!
 //!     // value should be the sum.
 //!     assert_eq!(19, factory.take());
@@ -153,27 +161,49 @@
 //! }
 //! ```
 
-#![feature(macro_rules)]
-#![feature(unboxed_closures)]
+#![allow(clippy::needless_doctest_main)]
 
 extern crate typedef;
 
-use std::any::{ Any };
-use std::boxed::{ BoxAny };
+use std::any::{ self, Any };
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::rc::Rc;
+use std::cell::RefCell;
 
 use typedef::{ TypeDef };
-use error::{ FactoryErrorKind };
-use aggregate::Aggregate;
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, ArgTypeMismatch, ArgKindMismatch, MissingArgument, UnknownArgument, TypeMismatch };
+use crate::aggregate::{ Aggregate, MapAggregate };
+use crate::sync::SendFactory;
+use crate::fallible::{ TryFactory };
+
+pub use crate::from_clone::{ cloneable, Cloneable };
+
+#[macro_use]
+mod macros;
 
 pub mod aggregate;
+pub mod annotated;
+pub mod autowire;
+pub mod dependencies;
 pub mod error;
+pub mod fallible;
+pub mod lazy;
+pub mod pool;
+pub mod registry;
+pub mod scope;
+pub mod singleton;
+pub mod sync;
 
 mod factory;
 mod from_clone;
 mod from_closure;
+#[cfg(test)]
+mod test_util;
 
 /// Gettable value trait.
-#[experimental]
 pub trait Getter<T> {
     /// Produce a new value.
     fn take(&self) -> T;
@@ -182,7 +212,7 @@ pub trait Getter<T> {
     ///
     /// This is kind of experimental solution - can not return plain traits
     /// as function result.
-    fn boxed_clone(&self) -> Box<Getter<T> + 'static>;
+    fn boxed_clone(&self) -> Box<dyn Getter<T>>;
 }
 
 /// A factory proxy.
@@ -190,19 +220,17 @@ pub trait Getter<T> {
 /// `Factory` proxy is used to return a concrete value from
 /// unknown source. `Factory` will always produce a new owned value -
 /// any other pattern can be implemented on top of that.
-#[stable]
-pub struct Factory<T:'static> {
-    getter: Box<Getter<T> + 'static>,
+pub struct Factory<T: 'static> {
+    getter: Box<dyn Getter<T>>,
 }
 
-#[stable]
-impl<'a, T: 'static> Factory<T> {
+impl<T: 'static> Factory<T> {
     /// Create a new `Factory`.
     ///
     /// Create a new factory from any type that implements `Getter` trait.
-    pub fn new(getter: Box<Getter<T> + 'static>) -> Factory<T> {
+    pub fn new(getter: Box<dyn Getter<T>>) -> Factory<T> {
         Factory::<T> {
-            getter: getter,
+            getter,
         }
     }
 
@@ -210,9 +238,81 @@ impl<'a, T: 'static> Factory<T> {
     pub fn take(&self) -> T {
         self.getter.take()
     }
+
+    /// Create a factory that builds the value once and returns its clones.
+    ///
+    /// All clones of the returned factory share the same value. See the
+    /// `singleton` module for details.
+    pub fn cached(self) -> Factory<T> where T: Clone {
+        Factory::new(Box::new(singleton::Singleton::new(self)))
+    }
+
+    /// Create a factory that builds the value once and returns `Rc`
+    /// references to it.
+    ///
+    /// All clones of the returned factory share the same value.
+    pub fn shared(self) -> Factory<Rc<T>> {
+        Factory::new(Box::new(singleton::RcSingleton::new(self)))
+    }
+
+    /// Create a factory that returns the same instance while the scope
+    /// is entered. See the `scope` module for details.
+    pub fn scoped(self, scope: &scope::Scope) -> Factory<T> where T: Clone {
+        Factory::new(Box::new(scope::Scoped::new(self, scope)))
+    }
+
+    /// Create a factory that transforms every produced value with closure.
+    ///
+    /// ```
+    /// use metafactory::{ metafactory, cloneable, AsFactoryExt };
+    ///
+    /// let factory = metafactory(cloneable(2i32)).new(Vec::new()).ok().unwrap()
+    ///     .as_factory_of::<i32>().unwrap()
+    ///     .map(|v| v.to_string());
+    ///
+    /// assert_eq!(factory.take(), "2");
+    /// ```
+    pub fn map<U: 'static, F>(self, closure: F) -> Factory<U>
+        where F: FnMut(T) -> U + 'static
+    {
+        Factory::new(Box::new(factory::Map {
+            source: self,
+            closure: Rc::new(RefCell::new(closure)),
+        }))
+    }
+
+    /// Create a factory that produces values of both factories as a pair.
+    pub fn zip<U: 'static>(self, other: Factory<U>) -> Factory<(T, U)> {
+        Factory::new(Box::new(factory::Zip {
+            first: self,
+            second: other,
+        }))
+    }
+
+    /// Create a factory that passes every produced value to closure, and
+    /// takes the final value from the factory it returns.
+    pub fn and_then<U: 'static, F>(self, closure: F) -> Factory<U>
+        where F: FnMut(T) -> Factory<U> + 'static
+    {
+        Factory::new(Box::new(factory::AndThen {
+            source: self,
+            closure: Rc::new(RefCell::new(closure)),
+        }))
+    }
+
+    /// Create a factory that shows a reference to every produced value
+    /// to closure before returning it.
+    pub fn inspect<F>(self, closure: F) -> Factory<T>
+        where F: FnMut(&T) + 'static
+    {
+        Factory::new(Box::new(factory::Inspect {
+            source: self,
+            closure: Rc::new(RefCell::new(closure)),
+        }))
+    }
 }
 
-impl<'a, T: 'static> Clone for Factory<T> {
+impl<T: 'static> Clone for Factory<T> {
     fn clone(&self) -> Factory<T> {
         Factory::<T> {
             getter: self.getter.boxed_clone(),
@@ -220,27 +320,282 @@ impl<'a, T: 'static> Clone for Factory<T> {
     }
 }
 
+/// Readable type name, captured with `std::any::type_name`.
+///
+/// `TypeDef` only knows type names on nightly compiler, so names are
+/// captured where the type is known and stored next to `TypeDef`. Names
+/// are only used in messages, and any two names compare equal.
+#[derive(Copy, Clone, Default)]
+pub struct TypeName(Option<fn() -> &'static str>);
+
+impl TypeName {
+    /// Capture the name of `T`.
+    pub fn of<T: ?Sized>() -> TypeName {
+        TypeName(Some(any::type_name::<T>))
+    }
+
+    /// Name of a type that is only known by `TypeDef`.
+    pub fn unknown() -> TypeName {
+        TypeName(None)
+    }
+
+    /// Return the name, if it is known.
+    pub fn get(&self) -> Option<&'static str> {
+        self.0.map(|name| name())
+    }
+
+    /// Return the name, or `TypeDef` string if the name is not known.
+    pub fn describe(&self, typedef: TypeDef) -> String {
+        match self.get() {
+            Some(name) => name.to_string(),
+            None => typedef.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("TypeName").field(&self.get()).finish()
+    }
+}
+
+impl PartialEq for TypeName {
+    fn eq(&self, _other: &TypeName) -> bool {
+        true
+    }
+}
+
+impl Eq for TypeName {}
+
+/// Kind of factory object: plain, thread-safe or fallible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FactoryKind {
+    /// `Factory`.
+    Plain,
+    /// `SendFactory`.
+    Send,
+    /// `TryFactory` with specified error type.
+    Fallible(TypeDef, TypeName),
+}
+
+impl FactoryKind {
+    /// Kind of `TryFactory` with error type `E`.
+    pub fn fallible<E: 'static>() -> FactoryKind {
+        FactoryKind::Fallible(TypeDef::of::<E>(), TypeName::of::<E>())
+    }
+}
+
+/// Type of values produced by a factory, together with the factory kind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FactoryType {
+    pub produced_type: TypeDef,
+    pub kind: FactoryKind,
+    pub type_name: TypeName,
+}
+
+impl FactoryType {
+    /// Describe factory of specified kind.
+    pub fn new(produced_type: TypeDef, kind: FactoryKind) -> FactoryType {
+        FactoryType {
+            produced_type,
+            kind,
+            type_name: TypeName::unknown(),
+        }
+    }
+
+    /// Describe `Factory<T>`.
+    pub fn of<T: 'static>() -> FactoryType {
+        FactoryType::new(TypeDef::of::<T>(), FactoryKind::Plain).with_type_name(TypeName::of::<T>())
+    }
+
+    /// Describe `SendFactory<T>`.
+    pub fn send_of<T: 'static>() -> FactoryType {
+        FactoryType::new(TypeDef::of::<T>(), FactoryKind::Send).with_type_name(TypeName::of::<T>())
+    }
+
+    /// Describe `TryFactory<T, E>`.
+    pub fn try_of<T: 'static, E: 'static>() -> FactoryType {
+        FactoryType::new(TypeDef::of::<T>(), FactoryKind::fallible::<E>()).with_type_name(TypeName::of::<T>())
+    }
+
+    /// Set the name of produced type.
+    pub fn with_type_name(mut self, type_name: TypeName) -> FactoryType {
+        self.type_name = type_name;
+        self
+    }
+}
+
+/// Written as the factory type, for example `TryFactory<i32, String>`.
+impl fmt::Display for FactoryType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let produced_type = self.type_name.describe(self.produced_type);
+        match self.kind {
+            FactoryKind::Plain => write!(f, "Factory<{}>", produced_type),
+            FactoryKind::Send => write!(f, "SendFactory<{}>", produced_type),
+            FactoryKind::Fallible(error_type, error_name) => {
+                write!(f, "TryFactory<{}, {}>", produced_type, error_name.describe(error_type))
+            },
+        }
+    }
+}
+
+/// Plain factory of specified type.
+impl From<TypeDef> for FactoryType {
+    fn from(produced_type: TypeDef) -> FactoryType {
+        FactoryType::new(produced_type, FactoryKind::Plain)
+    }
+}
+
+/// Factory of unknown type, together with the type of values it produces.
+///
+/// This is the type used to pass factories around when their type is
+/// only known at runtime.
+pub struct AnyFactory {
+    factory_type: FactoryType,
+    factory: Box<dyn Any>,
+}
+
+impl AnyFactory {
+    /// Wrap any factory object that produces values of specified type.
+    pub fn new(factory_type: FactoryType, factory: Box<dyn Any>) -> AnyFactory {
+        AnyFactory {
+            factory_type,
+            factory,
+        }
+    }
+
+    /// Return the type of values produced by the factory.
+    pub fn get_type(&self) -> TypeDef {
+        self.factory_type.produced_type
+    }
+
+    /// Return the produced type together with the factory kind.
+    pub fn get_factory_type(&self) -> FactoryType {
+        self.factory_type
+    }
+
+    /// Create an explicitly empty argument slot.
+    ///
+    /// Optional arguments of `lazy::lazy_metafactory` closures receive
+    /// `None` for empty slots, and required arguments return
+    /// `MissingArgument` error.
+    pub fn empty() -> AnyFactory {
+        AnyFactory::new(FactoryType::of::<EmptySlot>(), Box::new(EmptySlot))
+    }
+
+    /// Check if this is an empty argument slot.
+    pub fn is_empty(&self) -> bool {
+        self.factory.is::<EmptySlot>()
+    }
+
+    /// Return the wrapped factory object.
+    pub fn into_any(self) -> Box<dyn Any> {
+        self.factory
+    }
+}
+
+/// Placeholder stored in empty `AnyFactory`.
+struct EmptySlot;
+
+impl<T: 'static> From<Factory<T>> for AnyFactory {
+    fn from(factory: Factory<T>) -> AnyFactory {
+        AnyFactory::new(FactoryType::of::<T>(), Box::new(factory))
+    }
+}
+
+impl<T: 'static> From<SendFactory<T>> for AnyFactory {
+    fn from(factory: SendFactory<T>) -> AnyFactory {
+        AnyFactory::new(FactoryType::send_of::<T>(), Box::new(factory))
+    }
+}
+
+impl<T: 'static, E: 'static> From<TryFactory<T, E>> for AnyFactory {
+    fn from(factory: TryFactory<T, E>) -> AnyFactory {
+        AnyFactory::new(FactoryType::try_of::<T, E>(), Box::new(factory))
+    }
+}
+
 /// Downcast value to `Factory`.
+#[allow(clippy::wrong_self_convention)]
 pub trait AsFactoryExt {
     /// Downcast to factory and consume `Box<Any>`.
-    fn as_factory_of<T>(self) -> Option<Factory<T>>;
+    fn as_factory_of<T: 'static>(self) -> Option<Factory<T>>;
     /// Downcast to factory by creating factory clone.
-    fn as_factory_clone_of<T>(&self) -> Option<Factory<T>>;
+    fn as_factory_clone_of<T: 'static>(&self) -> Option<Factory<T>>;
+    /// Downcast to thread-safe factory and consume `Box<Any>`.
+    fn as_send_factory_of<T: 'static>(self) -> Option<SendFactory<T>>;
+    /// Downcast to thread-safe factory by creating factory clone.
+    fn as_send_factory_clone_of<T: 'static>(&self) -> Option<SendFactory<T>>;
+    /// Downcast to fallible factory and consume `Box<Any>`.
+    ///
+    /// Besides `TryFactory<T, E>`, this also accepts `Factory<T>` and
+    /// `Factory<Result<T, E>>`.
+    fn as_try_factory_of<T: 'static, E: 'static>(self) -> Option<TryFactory<T, E>>;
+    /// Downcast to fallible factory by creating factory clone.
+    fn as_try_factory_clone_of<T: 'static, E: 'static>(&self) -> Option<TryFactory<T, E>>;
 }
 
-impl AsFactoryExt for Box<Any> {
-    fn as_factory_of<'a, T: 'static>(self) -> Option<Factory<T>> {
-        match self.downcast::<Factory<T>>().ok() {
-            Some(val) => Some(*val),
-            None => None
+impl AsFactoryExt for Box<dyn Any> {
+    fn as_factory_of<T: 'static>(self) -> Option<Factory<T>> {
+        self.downcast::<Factory<T>>().ok().map(|val| *val)
+    }
+
+    fn as_factory_clone_of<T: 'static>(&self) -> Option<Factory<T>> {
+        self.downcast_ref::<Factory<T>>().cloned()
+    }
+
+    fn as_send_factory_of<T: 'static>(self) -> Option<SendFactory<T>> {
+        self.downcast::<SendFactory<T>>().ok().map(|val| *val)
+    }
+
+    fn as_send_factory_clone_of<T: 'static>(&self) -> Option<SendFactory<T>> {
+        self.downcast_ref::<SendFactory<T>>().cloned()
+    }
+
+    fn as_try_factory_of<T: 'static, E: 'static>(self) -> Option<TryFactory<T, E>> {
+        if self.is::<TryFactory<T, E>>() {
+            return self.downcast::<TryFactory<T, E>>().ok().map(|val| *val);
         }
+        if self.is::<Factory<Result<T, E>>>() {
+            return self.as_factory_of::<Result<T, E>>().map(TryFactory::from_result_factory);
+        }
+        self.as_factory_of::<T>().map(TryFactory::from_factory)
     }
 
-    fn as_factory_clone_of<'a, T: 'static>(&self) -> Option<Factory<T>> {
-        match self.downcast_ref::<Factory<T>>() {
-            Some(val) => Some(val.clone()),
-            None => None
+    fn as_try_factory_clone_of<T: 'static, E: 'static>(&self) -> Option<TryFactory<T, E>> {
+        if let Some(val) = self.downcast_ref::<TryFactory<T, E>>() {
+            return Some(val.clone());
+        }
+        if let Some(val) = self.as_factory_clone_of::<Result<T, E>>() {
+            return Some(TryFactory::from_result_factory(val));
         }
+        self.as_factory_clone_of::<T>().map(TryFactory::from_factory)
+    }
+}
+
+impl AsFactoryExt for AnyFactory {
+    fn as_factory_of<T: 'static>(self) -> Option<Factory<T>> {
+        self.factory.as_factory_of::<T>()
+    }
+
+    fn as_factory_clone_of<T: 'static>(&self) -> Option<Factory<T>> {
+        self.factory.as_factory_clone_of::<T>()
+    }
+
+    fn as_send_factory_of<T: 'static>(self) -> Option<SendFactory<T>> {
+        self.factory.as_send_factory_of::<T>()
+    }
+
+    fn as_send_factory_clone_of<T: 'static>(&self) -> Option<SendFactory<T>> {
+        self.factory.as_send_factory_clone_of::<T>()
+    }
+
+    fn as_try_factory_of<T: 'static, E: 'static>(self) -> Option<TryFactory<T, E>> {
+        self.factory.as_try_factory_of::<T, E>()
+    }
+
+    fn as_try_factory_clone_of<T: 'static, E: 'static>(&self) -> Option<TryFactory<T, E>> {
+        self.factory.as_try_factory_clone_of::<T, E>()
     }
 }
 
@@ -262,7 +617,7 @@ impl AsFactoryExt for Box<Any> {
 ///
 /// There are separate traits for `MetaFactory` and "real" `Factory`.
 /// `MetaFactory` is used to build a real `Factory` by passing all the
-/// required constructor arguments as factories under `Vec<Box<Any>>`.
+/// required constructor arguments as factories under `Vec<AnyFactory>`.
 ///
 /// Internaly, all parent `Factory`s will be downcasted to correct types
 /// and stored inside returned `Factory`'s scope, so that all of them
@@ -276,59 +631,394 @@ impl AsFactoryExt for Box<Any> {
 /// correct, and then create an actual getter for the value:
 ///
 /// ```
-/// use metafactory::{ metafactory, AsFactoryExt };
+/// use metafactory::{ metafactory, cloneable, AsFactoryExt };
 ///
-/// let metafactory = metafactory(5i);
-/// assert!(metafactory.get_type().is::<int>());
-/// assert!(metafactory.get_arg_types().len() == 0); // clonable int has no arguments
+/// let metafactory = metafactory(cloneable(5i32));
+/// assert!(metafactory.get_type().is::<i32>());
+/// assert!(metafactory.get_arg_types().len() == 0); // clonable i32 has no arguments
 ///
 /// let factory = metafactory
 ///     .new(
 ///         Vec::new() // No arguments in this case.
 ///     )
 ///     .ok().unwrap()
-///     .as_factory_of::<int>()
+///     .as_factory_of::<i32>()
 ///     .unwrap();
 ///
-/// assert_eq!(factory.take(), 5i);
+/// assert_eq!(factory.take(), 5);
 /// ```
-#[unstable]
+#[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
 pub trait MetaFactory {
-    #[unstable]
     fn get_type(&self) -> TypeDef;
-    #[unstable]
     fn get_arg_types(&self) -> Vec<TypeDef>;
-    #[unstable]
-    fn new(&self, arg_getters: Vec<Box<Any>>) -> Result<Box<Any>, FactoryErrorKind>;
-    #[unstable]
+
+    /// Return the type and kind of factories returned by `new`.
+    ///
+    /// Most metafactories return plain `Factory`, fallible ones return
+    /// `TryFactory`. By default this is a plain factory of `get_type`,
+    /// without a readable type name.
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::from(self.get_type())
+    }
+
+    /// Describe every argument.
+    ///
+    /// By default all arguments returned by `get_arg_types` are required.
+    fn get_args(&self) -> Vec<ArgDescriptor> {
+        self.get_arg_types().into_iter().map(ArgDescriptor::required).collect()
+    }
+
+    fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind>;
     fn new_aggregate(&self) -> Aggregate<'static>;
+
+    /// Check if factories of specified types would be accepted by `new`,
+    /// without constructing anything.
+    ///
+    /// Returns the same error `new` would return for arguments of these
+    /// types. By default arguments are checked against `get_args`, and
+    /// the first problem reported by `validate` is returned.
+    ///
+    /// ```
+    /// use metafactory::{ metafactory, FactoryType };
+    ///
+    /// let meta_sum = metafactory(|a: i32, b: i32| a + b);
+    ///
+    /// assert!(meta_sum.check_args(&[FactoryType::of::<i32>(), FactoryType::of::<i32>()]).is_ok());
+    /// assert!(meta_sum.check_args(&[FactoryType::of::<i32>(), FactoryType::of::<bool>()]).is_err());
+    /// assert!(meta_sum.check_args(&[FactoryType::of::<i32>(), FactoryType::send_of::<i32>()]).is_err());
+    /// ```
+    fn check_args(&self, arg_types: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+        check_arg_types(&self.get_args(), arg_types)
+    }
+
+    /// Create a new factory, binding argument factories by argument names.
+    ///
+    /// Names are taken from `get_args`, so metafactory arguments should be
+    /// named, for example with `annotated::Annotated`. Optional arguments
+    /// that are not specified are left empty.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use metafactory::{ metafactory, argless_as_factory, cloneable, AsFactoryExt };
+    /// use metafactory::annotated::Annotated;
+    ///
+    /// let meta_area = Annotated::new(metafactory(|w: i32, h: i32| w * h))
+    ///     .arg_names(&["width", "height"]).ok().unwrap()
+    ///     .build();
+    ///
+    /// let mut args = HashMap::new();
+    /// args.insert("height".to_string(), argless_as_factory(cloneable(3i32)));
+    /// args.insert("width".to_string(), argless_as_factory(cloneable(4i32)));
+    ///
+    /// let area = meta_area.new_named(args).ok().unwrap().as_factory_of::<i32>().unwrap();
+    /// assert_eq!(area.take(), 12);
+    /// ```
+    fn new_named(&self, named_args: HashMap<String, AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        self.new(bind_named_args(&self.get_args(), named_args)?)
+    }
+
+    /// Check if factories of specified types could be used as arguments,
+    /// without building anything.
+    ///
+    /// Unlike `new`, this does not stop at the first problem: all argument
+    /// count, type and kind mismatches are collected with
+    /// `FactoryErrorKind::combine`. A single problem is returned as is, and
+    /// several problems are returned together as `Multiple` error.
+    ///
+    /// ```
+    /// use metafactory::{ metafactory, FactoryType };
+    /// use metafactory::error::FactoryErrorKind;
+    ///
+    /// let meta_sum = metafactory(|a: i8, b: i8, c: i8| a + b + c);
+    ///
+    /// match meta_sum.validate(&[FactoryType::of::<i8>(), FactoryType::of::<bool>(), FactoryType::of::<f64>()]) {
+    ///     Err(FactoryErrorKind::Multiple(errors)) => assert_eq!(errors.len(), 2),
+    ///     _ => panic!("expected two errors"),
+    /// }
+    ///
+    /// match meta_sum.validate(&[FactoryType::of::<i8>(), FactoryType::of::<i8>(), FactoryType::of::<f64>()]) {
+    ///     Err(e) => assert_eq!(e.to_string(), "argument 2 expected i8, got f64"),
+    ///     _ => panic!("expected one error"),
+    /// }
+    /// ```
+    fn validate(&self, arg_types: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+        validate_arg_types(&self.get_args(), arg_types)
+    }
+}
+
+impl dyn MetaFactory {
+    /// Create an aggregate for the produced type that collects values into
+    /// container `C`, for example `HashSet<T>`.
+    ///
+    /// Returns `TypeMismatch` error if `C` does not contain the produced
+    /// type. The aggregate expects plain `Factory` children, like
+    /// `Aggregate::new_collect`, so factories of fallible metafactories
+    /// are rejected with `ChildKindMismatch` error. `SyncMetaFactory` has
+    /// the same method for `SendFactory` children.
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    /// use metafactory::{ metafactory, AsFactoryExt };
+    ///
+    /// let meta_flag = metafactory(|| true);
+    ///
+    /// let flags = meta_flag.new_aggregate_of::<HashSet<bool>>().ok().unwrap()
+    ///     .new_factory(vec![meta_flag.new(Vec::new()).ok().unwrap()]).ok().unwrap()
+    ///     .as_factory_of::<HashSet<bool>>().unwrap();
+    ///
+    /// assert!(flags.take().contains(&true));
+    /// ```
+    pub fn new_aggregate_of<C>(&self) -> Result<Aggregate<'static>, FactoryErrorKind>
+        where C: 'static + IntoIterator + FromIterator<<C as IntoIterator>::Item>
+    {
+        expect_produced_type::<C::Item>(self.get_factory_type())?;
+        Ok(Aggregate::new_collect::<C::Item, C>())
+    }
+
+    /// Create a keyed aggregate for the produced type that collects values
+    /// into `HashMap<K, T>`.
+    ///
+    /// Returns `TypeMismatch` error if `T` is not the produced type. The
+    /// aggregate expects plain `Factory` children, like `Aggregate::new_map`.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use metafactory::{ metafactory, AsFactoryExt };
+    ///
+    /// let meta_port = metafactory(|| 80u16);
+    ///
+    /// let ports = meta_port.new_map_aggregate::<&'static str, u16>().ok().unwrap()
+    ///     .new_factory(vec![("http", meta_port.new(Vec::new()).ok().unwrap())]).ok().unwrap()
+    ///     .as_factory_of::<HashMap<&'static str, u16>>().unwrap();
+    ///
+    /// assert_eq!(ports.take()["http"], 80);
+    /// ```
+    pub fn new_map_aggregate<K, T>(&self) -> Result<MapAggregate<'static, K>, FactoryErrorKind>
+        where K: 'static + Clone + Eq + Hash, T: 'static
+    {
+        expect_produced_type::<T>(self.get_factory_type())?;
+        Ok(Aggregate::new_map::<K, T>())
+    }
+}
+
+/// Return `TypeMismatch` error if metafactory does not produce expected type.
+fn expect_produced_type<T: 'static>(produced: FactoryType) -> Result<(), FactoryErrorKind> {
+    if produced.produced_type != TypeDef::of::<T>() {
+        let mut error = TypeMismatch::new(TypeDef::of::<T>(), produced.produced_type);
+        error.expected_type_name = TypeName::of::<T>();
+        error.actual_type_name = produced.type_name;
+        return Err(FactoryErrorKind::TypeMismatch(error));
+    }
+    Ok(())
+}
+
+/// Description of a metafactory argument.
+///
+/// Names and descriptions are not known to metafactories created from
+/// closures, they can be added with `annotated::Annotated`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgDescriptor {
+    /// Type of the argument value.
+    pub arg_type: TypeDef,
+    /// Name of `arg_type` used in error messages.
+    pub type_name: TypeName,
+    /// Argument can be left without a factory. Only `Option<A>` parameters
+    /// of `lazy::lazy_metafactory` closures are optional.
+    pub optional: bool,
+    /// Argument name used in error messages.
+    pub name: Option<String>,
+    /// Human readable description.
+    pub description: Option<String>,
+    /// Kind of `arg_type` factory expected for this argument.
+    pub kind: FactoryKind,
+    /// Other factory types accepted for this argument, besides `arg_type`.
+    pub accepted_types: Vec<FactoryType>,
+}
+
+impl ArgDescriptor {
+    /// Describe a required argument.
+    pub fn required(arg_type: TypeDef) -> ArgDescriptor {
+        ArgDescriptor {
+            arg_type,
+            type_name: TypeName::unknown(),
+            optional: false,
+            name: None,
+            description: None,
+            kind: FactoryKind::Plain,
+            accepted_types: Vec::new(),
+        }
+    }
+
+    /// Describe a required argument of type `A`, with readable type name.
+    pub fn of<A: 'static>() -> ArgDescriptor {
+        ArgDescriptor {
+            type_name: TypeName::of::<A>(),
+            ..ArgDescriptor::required(TypeDef::of::<A>())
+        }
+    }
+
+    /// Describe an optional argument.
+    pub fn optional(arg_type: TypeDef) -> ArgDescriptor {
+        ArgDescriptor {
+            optional: true,
+            ..ArgDescriptor::required(arg_type)
+        }
+    }
+
+    /// Set argument name.
+    pub fn with_name(mut self, name: &str) -> ArgDescriptor {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Set argument description.
+    pub fn with_description(mut self, description: &str) -> ArgDescriptor {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Set expected factory kind, the default is `FactoryKind::Plain`.
+    pub fn with_kind(mut self, kind: FactoryKind) -> ArgDescriptor {
+        self.kind = kind;
+        self
+    }
+
+    /// Accept factories of another type or kind for this argument.
+    pub fn accepting(mut self, accepted_type: FactoryType) -> ArgDescriptor {
+        self.accepted_types.push(accepted_type);
+        self
+    }
+
+    /// Check if factory of specified type can be used for this argument.
+    pub fn accepts(&self, factory_type: FactoryType) -> bool {
+        self.expected_factory_type() == factory_type || self.accepted_types.contains(&factory_type)
+    }
+
+    /// Return the type of `arg_type` factory of expected kind.
+    fn expected_factory_type(&self) -> FactoryType {
+        FactoryType::new(self.arg_type, self.kind).with_type_name(self.type_name)
+    }
+}
+
+/// Order named argument factories by argument position.
+///
+/// Returns `UnknownArgument` error for a name that does not belong to any
+/// argument, and `MissingArgument` error for unspecified required argument.
+fn bind_named_args(args: &[ArgDescriptor], mut named_args: HashMap<String, AnyFactory>) -> Result<Vec<AnyFactory>, FactoryErrorKind> {
+    let mut unknown: Vec<&String> = named_args.keys()
+        .filter(|name| !args.iter().any(|a| a.name.as_ref() == Some(*name)))
+        .collect();
+    unknown.sort();
+    if let Some(name) = unknown.first() {
+        return Err(FactoryErrorKind::UnknownArgument(UnknownArgument::new(name)));
+    }
+
+    let mut arg_getters = Vec::with_capacity(args.len());
+    for (index, arg) in args.iter().enumerate() {
+        match arg.name.as_ref().and_then(|name| named_args.remove(name)) {
+            Some(factory) => arg_getters.push(factory),
+            None if arg.optional => arg_getters.push(AnyFactory::empty()),
+            None => {
+                let mut error = MissingArgument::new(arg.arg_type, index);
+                error.expected_type_name = arg.type_name;
+                error.argument_name = arg.name.clone();
+                return Err(FactoryErrorKind::MissingArgument(error));
+            },
+        }
+    }
+
+    Ok(arg_getters)
+}
+
+/// Return the smallest number of arguments that should be specified.
+///
+/// Only trailing optional arguments can be omitted.
+fn required_arg_count(args: &[ArgDescriptor]) -> usize {
+    args.iter().rposition(|a| !a.optional).map_or(0, |i| i + 1)
+}
+
+/// Compare expected arguments with specified types and return all
+/// mismatches.
+fn validate_arg_types(expected: &[ArgDescriptor], specified: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+    match FactoryErrorKind::combine(arg_type_errors(expected, specified)) {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// Compare expected arguments with specified types and return the first
+/// mismatch.
+fn check_arg_types(expected: &[ArgDescriptor], specified: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+    match arg_type_errors(expected, specified).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// List argument count, type and kind mismatches, in the order `new`
+/// checks them.
+fn arg_type_errors(expected: &[ArgDescriptor], specified: &[FactoryType]) -> Vec<FactoryErrorKind> {
+    let mut errors = Vec::new();
+
+    if specified.len() < required_arg_count(expected) || specified.len() > expected.len() {
+        errors.push(FactoryErrorKind::ArgCountMismatch(
+            ArgCountMismatch::new(expected.len(), specified.len())
+        ));
+    }
+
+    for (index, (expected_arg, actual_type)) in expected.iter().zip(specified.iter()).enumerate() {
+        if !expected_arg.accepts(*actual_type) {
+            let error = arg_mismatch(expected_arg.expected_factory_type(), index, *actual_type);
+            errors.push(error.with_arg_names(&|_| expected_arg.name.clone()));
+        }
+    }
+
+    errors
+}
+
+/// Return `ArgKindMismatch` error if argument factory produces expected
+/// type, and `ArgTypeMismatch` error otherwise.
+fn arg_mismatch(expected: FactoryType, index: usize, actual: FactoryType) -> FactoryErrorKind {
+    if expected.produced_type == actual.produced_type {
+        let mut error = ArgKindMismatch::new(expected.produced_type, index, expected.kind, actual.kind);
+        error.produced_type_name = expected.type_name;
+        FactoryErrorKind::ArgKindMismatch(error)
+    } else {
+        let mut error = ArgTypeMismatch::new(expected.produced_type, index, actual.produced_type);
+        error.expected_type_name = expected.type_name;
+        error.actual_type_name = actual.type_name;
+        FactoryErrorKind::ArgTypeMismatch(error)
+    }
 }
 
 /// Trait for values convertable to `MetaFactory`.
 ///
 /// This trait is implemented for values that can be used as
 /// sources for object creation.
-#[unstable]
-pub trait ToMetaFactory {
+///
+/// The `Args` parameter only tells implementations apart: closures
+/// use their signature (for example `fn(A1, A2) -> T`), so that
+/// a closure of any arity can be converted. It is inferred and never
+/// needs to be written by hand.
+pub trait ToMetaFactory<Args> {
     /// Creates a `MetaFactory` that has information about object
     /// constructor: produced object type, argument types, and
     /// a method to get this getter.
-    #[unstable]
-    fn to_metafactory<'a>(self) -> Box<MetaFactory + 'a>;
+    fn to_metafactory(self) -> Box<dyn MetaFactory>;
 }
 
 /// Create a new `MetaFactory` for any compatible value source.
 ///
 /// Compatible value type must have `ToMetaFactory` implementation.
 /// Supported sources are in submodules, look at "clone" for simpliest example.
-pub fn metafactory<'r, T: ToMetaFactory>(any: T) -> Box<MetaFactory + 'r> {
+pub fn metafactory<Args, T: ToMetaFactory<Args>>(any: T) -> Box<dyn MetaFactory> {
     any.to_metafactory()
 }
 
-/// Create a new `MetaFactory` and return `Factory` in `Box<Any>` for source with no arguments.
+/// Create a new `MetaFactory` and return `Factory` in `AnyFactory` for source with no arguments.
 ///
 /// Compatible value type must have `ToMetaFactory` implementation.
 /// Supported sources are in submodules, look at "clone" for simpliest example.
-pub fn argless_as_factory<T: ToMetaFactory>(any: T) -> Box<Any> {
+pub fn argless_as_factory<Args, T: ToMetaFactory<Args>>(any: T) -> AnyFactory {
     any.to_metafactory().new(Vec::new()).ok().unwrap()
 }
@@ -0,0 +1,63 @@
+//! Macros shared by metafactory implementations.
+
+/// Return `ArgCountMismatch` error if argument counts do not match.
+macro_rules! assert_arg_count(
+    ($expected:expr, $specified:expr)
+    =>
+    (
+        if $expected != $specified {
+            return Err(
+                FactoryErrorKind::ArgCountMismatch(
+                    ArgCountMismatch::new($expected, $specified)
+                )
+            )
+        }
+    )
+);
+
+/// Downcast argument to factory of specified type, or return
+/// `MissingArgument` error for empty slot, `ArgKindMismatch` error for
+/// factory of another kind, and `ArgTypeMismatch` error for factory of
+/// another type.
+///
+/// The downcast method defaults to `as_factory_of` of `FactoryKind::Plain`.
+/// Otherwise the expected kind is followed by the downcast method and its
+/// additional type parameters.
+macro_rules! try_unwrap_factory(
+    ($T:ty, $factory:expr, $index:ident)
+    =>
+    (
+        try_unwrap_factory!($T, $factory, $index, FactoryKind::Plain, as_factory_of)
+    );
+    ($T:ty, $factory:expr, $index:ident, $kind:expr, $as_factory:ident $(, $P:ty)*)
+    =>
+    (
+        {
+            if $factory.is_empty() {
+                return Err(
+                    FactoryErrorKind::MissingArgument(MissingArgument {
+                        expected_type_name: crate::TypeName::of::<$T>(),
+                        ..MissingArgument::new(TypeDef::of::<$T>(), $index)
+                    })
+                );
+            }
+            let actual_type = $factory.get_factory_type();
+            match $factory.$as_factory::<$T $(, $P)*>() {
+                Some(factory) => factory,
+                None => {
+                    return Err(crate::arg_mismatch(
+                        FactoryType::new(TypeDef::of::<$T>(), $kind).with_type_name(crate::TypeName::of::<$T>()),
+                        $index,
+                        actual_type
+                    ));
+                }
+            }
+        }
+    )
+);
+
+/// Count comma-separated expressions.
+macro_rules! count_exprs {
+    () => (0);
+    ($head:expr $(, $tail:expr)*) => (1 + count_exprs!($($tail),*));
+}
@@ -0,0 +1,335 @@
+//! Implements a pool that recycles produced values.
+//!
+//! `Pool` takes values from a source factory and hands them out wrapped
+//! in `Pooled` guards. When the guard is dropped, the value is reset and
+//! returned to the pool, so the next `take` can reuse it instead of
+//! building a new one.
+//!
+//! The pool is a `Getter<Pooled<T>>`, so its factory can be used as an
+//! argument to closure metafactories.
+//!
+//! ```
+//! use metafactory::{ metafactory, AsFactoryExt };
+//! use metafactory::pool::{ Pool, Pooled };
+//!
+//! fn main() {
+//!     let buffers = metafactory(|| Vec::<u8>::with_capacity(1024))
+//!         .new(Vec::new()).ok().unwrap()
+//!         .as_factory_of::<Vec<u8>>().unwrap();
+//!
+//!     let pool = Pool::new(buffers)
+//!         .with_max_size(4)
+//!         .with_reset(|buffer: &mut Vec<u8>| buffer.clear());
+//!
+//!     let render = metafactory(|mut buffer: Pooled<Vec<u8>>| {
+//!         buffer.extend_from_slice(b"hello");
+//!         buffer.len()
+//!     }).new(vec![pool.factory().into()]).ok().unwrap()
+//!         .as_factory_of::<usize>().unwrap();
+//!
+//!     assert_eq!(render.take(), 5);
+//!     assert_eq!(render.take(), 5);
+//!
+//!     assert_eq!(pool.stats().created, 1);
+//!     assert_eq!(pool.stats().reused, 1);
+//! }
+//! ```
+
+use std::rc::Rc;
+use std::cell::{ Cell, RefCell };
+use std::ops::{ Deref, DerefMut };
+
+use crate::{ Factory, Getter };
+
+/// Pool usage counters.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PoolStats {
+    /// Values built by the source factory.
+    pub created: usize,
+    /// Values taken from the pool instead of building new ones.
+    pub reused: usize,
+    /// Values returned to the pool.
+    pub returned: usize,
+    /// Values dropped because the pool was full, or because they were
+    /// returned while the reset closure was running.
+    pub discarded: usize,
+}
+
+/// Closure that prepares returned value for reuse.
+///
+/// It is shared, so that it can be called without borrowing the pool state.
+type ResetFn<T> = Rc<RefCell<dyn FnMut(&mut T)>>;
+
+struct PoolState<T: 'static> {
+    source: Factory<T>,
+    idle: RefCell<Vec<T>>,
+    max_size: Cell<usize>,
+    reset: RefCell<Option<ResetFn<T>>>,
+    stats: Cell<PoolStats>,
+}
+
+impl<T: 'static> PoolState<T> {
+    fn update_stats<F: FnOnce(&mut PoolStats)>(&self, update: F) {
+        let mut stats = self.stats.get();
+        update(&mut stats);
+        self.stats.set(stats);
+    }
+
+    fn has_room(&self) -> bool {
+        self.idle.borrow().len() < self.max_size.get()
+    }
+
+    fn give_back(&self, mut value: T) {
+        if !self.has_room() {
+            self.update_stats(|s| s.discarded += 1);
+            return;
+        }
+
+        // The reset closure may use the pool, so no pool state is borrowed
+        // while it runs. A value returned by the reset closure itself can
+        // not be reset, so it is discarded.
+        let reset = self.reset.borrow().clone();
+        if let Some(reset) = reset {
+            match reset.try_borrow_mut() {
+                Ok(mut reset) => (*reset)(&mut value),
+                Err(_) => {
+                    self.update_stats(|s| s.discarded += 1);
+                    return;
+                },
+            }
+        }
+
+        if self.has_room() {
+            self.idle.borrow_mut().push(value);
+            self.update_stats(|s| s.returned += 1);
+        } else {
+            self.update_stats(|s| s.discarded += 1);
+        }
+    }
+}
+
+/// Getter that reuses values returned by `Pooled` guards.
+///
+/// Clones of the pool share the same idle values and statistics.
+pub struct Pool<T: 'static> {
+    state: Rc<PoolState<T>>,
+}
+
+impl<T: 'static> Pool<T> {
+    /// Create a new pool that builds values with source factory.
+    ///
+    /// The number of idle values kept by the pool is not limited.
+    pub fn new(source: Factory<T>) -> Pool<T> {
+        Pool {
+            state: Rc::new(PoolState {
+                source,
+                idle: RefCell::new(Vec::new()),
+                max_size: Cell::new(usize::MAX),
+                reset: RefCell::new(None),
+                stats: Cell::new(PoolStats::default()),
+            }),
+        }
+    }
+
+    /// Limit the number of idle values kept by the pool.
+    ///
+    /// Values returned to the full pool are dropped.
+    pub fn with_max_size(self, max_size: usize) -> Pool<T> {
+        self.state.max_size.set(max_size);
+        self.state.idle.borrow_mut().truncate(max_size);
+        self
+    }
+
+    /// Set the closure that prepares every returned value for reuse.
+    pub fn with_reset<F>(self, reset: F) -> Pool<T>
+        where F: FnMut(&mut T) + 'static
+    {
+        *self.state.reset.borrow_mut() = Some(Rc::new(RefCell::new(reset)));
+        self
+    }
+
+    /// Create a factory that takes values from this pool.
+    pub fn factory(&self) -> Factory<Pooled<T>> {
+        Factory::new(self.boxed_clone())
+    }
+
+    /// Get usage counters.
+    pub fn stats(&self) -> PoolStats {
+        self.state.stats.get()
+    }
+
+    /// Get the number of values waiting in the pool.
+    pub fn idle_count(&self) -> usize {
+        self.state.idle.borrow().len()
+    }
+}
+
+impl<T: 'static> Getter<Pooled<T>> for Pool<T> {
+    fn take(&self) -> Pooled<T> {
+        let reused = self.state.idle.borrow_mut().pop();
+        let value = match reused {
+            Some(value) => {
+                self.state.update_stats(|s| s.reused += 1);
+                value
+            },
+            None => {
+                let value = self.state.source.take();
+                self.state.update_stats(|s| s.created += 1);
+                value
+            },
+        };
+
+        Pooled {
+            value: Some(value),
+            state: self.state.clone(),
+        }
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<Pooled<T>>> {
+        Box::new(Pool {
+            state: self.state.clone(),
+        })
+    }
+}
+
+/// Value borrowed from the pool. It is returned to the pool on drop.
+pub struct Pooled<T: 'static> {
+    value: Option<T>,
+    state: Rc<PoolState<T>>,
+}
+
+impl<T: 'static> Pooled<T> {
+    /// Take the value out, so it is never returned to the pool.
+    pub fn detach(mut self) -> T {
+        self.value.take().unwrap()
+    }
+}
+
+impl<T: 'static> Deref for Pooled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T: 'static> DerefMut for Pooled<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T: 'static> Drop for Pooled<T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            self.state.give_back(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::{ Cell, RefCell };
+    use crate::test_util::counting;
+    use super::{ Pool, Pooled, PoolStats };
+
+    #[test]
+    fn should_reuse_returned_value() {
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))));
+        let factory = pool.factory();
+
+        assert_eq!(*factory.take(), 1);
+        assert_eq!(*factory.take(), 1);
+        assert_eq!(pool.stats(), PoolStats { created: 1, reused: 1, returned: 2, discarded: 0 });
+    }
+
+    #[test]
+    fn should_build_new_value_while_others_are_borrowed() {
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))));
+        let factory = pool.factory();
+
+        let a = factory.take();
+        let b = factory.clone().take();
+
+        assert_eq!((*a, *b), (1, 2));
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 2);
+    }
+
+    #[test]
+    fn should_discard_values_over_max_size() {
+        let pool = Pool::new(counting(Rc::new(Cell::new(0)))).with_max_size(1);
+        let factory = pool.factory();
+
+        let a = factory.take();
+        let b = factory.take();
+        drop(a);
+        drop(b);
+
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(pool.stats().discarded, 1);
+    }
+
+    #[test]
+    fn should_reset_returned_value() {
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))))
+            .with_reset(|value: &mut i32| *value = 0);
+        let factory = pool.factory();
+
+        let mut value = factory.take();
+        *value += 10;
+        assert_eq!(*value, 11);
+        drop(value);
+
+        assert_eq!(*factory.take(), 0);
+    }
+
+    #[test]
+    fn should_not_reset_discarded_values() {
+        let resets = Rc::new(Cell::new(0));
+        let resets_by_closure = resets.clone();
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))))
+            .with_max_size(1)
+            .with_reset(move |_: &mut i32| resets_by_closure.set(resets_by_closure.get() + 1));
+        let factory = pool.factory();
+
+        let a = factory.take();
+        let b = factory.take();
+        drop(a);
+        drop(b);
+
+        assert_eq!(resets.get(), 1);
+        assert_eq!(pool.stats().discarded, 1);
+    }
+
+    #[test]
+    fn reset_closure_should_be_able_to_use_pool() {
+        let held: Rc<RefCell<Option<Pooled<i32>>>> = Rc::new(RefCell::new(None));
+        let held_by_closure = held.clone();
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))))
+            .with_reset(move |value: &mut i32| {
+                drop(held_by_closure.borrow_mut().take());
+                *value = 0;
+            });
+        let factory = pool.factory();
+
+        let a = factory.take();
+        *held.borrow_mut() = Some(factory.take());
+        drop(a);
+
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(pool.stats(), PoolStats { created: 2, reused: 0, returned: 1, discarded: 1 });
+        assert_eq!(*factory.take(), 0);
+    }
+
+    #[test]
+    fn detached_value_should_not_return_to_pool() {
+        let pool = Pool::new(counting(Rc::new(Cell::new(0))));
+
+        assert_eq!(pool.factory().take().detach(), 1);
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(pool.stats().returned, 0);
+    }
+}
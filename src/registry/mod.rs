@@ -0,0 +1,298 @@
+//! Implements a registry of named metafactories that can build
+//! factory trees automatically.
+//!
+//! Every metafactory is registered under a name, together with the names
+//! of other definitions that should be used as its arguments. Then any
+//! definition can be resolved to a `Factory`, and all its dependencies will
+//! be resolved and plugged in.
+//!
+//! ```
+//! use metafactory::{ metafactory, cloneable };
+//! use metafactory::registry::Registry;
+//!
+//! fn main() {
+//!     let mut registry = Registry::new();
+//!
+//!     registry.register("width", metafactory(cloneable(4i32)), &[]);
+//!     registry.register("height", metafactory(|| 3i32), &[]);
+//!     registry.register(
+//!         "area",
+//!         metafactory(|w: i32, h: i32| w * h),
+//!         &["width", "height"]
+//!     );
+//!
+//!     let area = registry.resolve::<i32>("area").ok().unwrap();
+//!
+//!     assert_eq!(area.take(), 12);
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use typedef::TypeDef;
+
+use crate::{ MetaFactory, AnyFactory, Factory, TypeName, AsFactoryExt };
+use crate::error::FactoryErrorKind;
+use crate::error::{ NotRegistered, TypeMismatch, DefinitionError, DependencyCycle };
+
+/// Registered metafactory and the names of its argument sources.
+struct Definition {
+    metafactory: Box<dyn MetaFactory>,
+    arguments: Vec<String>,
+}
+
+/// Container of named metafactories.
+#[derive(Default)]
+pub struct Registry {
+    definitions: HashMap<String, Definition>,
+}
+
+impl Registry {
+    /// Create new empty registry.
+    pub fn new() -> Registry {
+        Registry {
+            definitions: HashMap::new(),
+        }
+    }
+
+    /// Register metafactory under specified name.
+    ///
+    /// Each of the `arguments` is a name of definition that will be used
+    /// as argument at the same index. Registering the same name again
+    /// replaces previous definition.
+    pub fn register(&mut self, name: &str, metafactory: Box<dyn MetaFactory>, arguments: &[&str]) {
+        self.definitions.insert(
+            name.to_string(),
+            Definition {
+                metafactory,
+                arguments: arguments.iter().map(|a| a.to_string()).collect(),
+            }
+        );
+    }
+
+    /// Check if there is a definition with specified name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.definitions.contains_key(name)
+    }
+
+    /// Return value type of the named definition.
+    pub fn get_type(&self, name: &str) -> Option<TypeDef> {
+        self.definitions.get(name).map(|d| d.metafactory.get_type())
+    }
+
+    /// Resolve named definition and downcast it to `Factory<T>`.
+    pub fn resolve<T: 'static>(&self, name: &str) -> Result<Factory<T>, FactoryErrorKind> {
+        let factory = self.resolve_any(name)?;
+        let actual = factory.get_factory_type();
+        match factory.as_factory_of::<T>() {
+            Some(factory) => Ok(factory),
+            None => Err(FactoryErrorKind::DefinitionError(DefinitionError::new(
+                name,
+                FactoryErrorKind::TypeMismatch(TypeMismatch {
+                    expected_type_name: TypeName::of::<T>(),
+                    actual_type_name: actual.type_name,
+                    ..TypeMismatch::new(TypeDef::of::<T>(), actual.produced_type)
+                })
+            ))),
+        }
+    }
+
+    /// Resolve named definition with all its dependencies.
+    ///
+    /// Argument types are validated against argument definitions, and
+    /// dependency cycles are detected before any factory is created.
+    pub fn resolve_any(&self, name: &str) -> Result<AnyFactory, FactoryErrorKind> {
+        self.check_cycles(name, &mut Vec::new())?;
+        self.build(name)
+    }
+
+    fn check_cycles<'a>(&'a self, name: &'a str, stack: &mut Vec<&'a str>) -> Result<(), FactoryErrorKind> {
+        if let Some(position) = stack.iter().position(|n| *n == name) {
+            let mut path: Vec<String> = stack[position..].iter().map(|n| n.to_string()).collect();
+            path.push(name.to_string());
+            return Err(FactoryErrorKind::DependencyCycle(DependencyCycle::new(path)));
+        }
+
+        if let Some(definition) = self.definitions.get(name) {
+            stack.push(name);
+            for argument in definition.arguments.iter() {
+                self.check_cycles(argument, stack)?;
+            }
+            stack.pop();
+        }
+
+        Ok(())
+    }
+
+    fn build(&self, name: &str) -> Result<AnyFactory, FactoryErrorKind> {
+        let definition = match self.definitions.get(name) {
+            Some(definition) => definition,
+            None => return Err(FactoryErrorKind::NotRegistered(NotRegistered::new(name))),
+        };
+
+        self.validate_arguments(definition)
+            .and_then(|_| {
+                let arguments = definition.arguments.iter()
+                    .map(|argument| self.build(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                definition.metafactory.new(arguments)
+            })
+            .map_err(|e| FactoryErrorKind::DefinitionError(DefinitionError::new(name, e)))
+    }
+
+    fn validate_arguments(&self, definition: &Definition) -> Result<(), FactoryErrorKind> {
+        let arg_types = definition.arguments.iter()
+            .map(|argument| self.definitions.get(argument)
+                .map(|d| d.metafactory.get_factory_type())
+                .ok_or_else(|| FactoryErrorKind::NotRegistered(NotRegistered::new(argument))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        definition.metafactory.check_args(&arg_types)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::ParseIntError;
+    use typedef::TypeDef;
+    use crate::{ metafactory, cloneable, FactoryKind, AsFactoryExt };
+    use crate::error::FactoryErrorKind;
+    use crate::fallible::try_metafactory;
+    use super::Registry;
+
+    #[test]
+    fn should_resolve_nested_definitions() {
+        let mut registry = Registry::new();
+        registry.register("one", metafactory(cloneable(1i32)), &[]);
+        registry.register("sum", metafactory(|a: i32, b: i32| a + b), &["one", "one"]);
+        registry.register("text", metafactory(|s: i32| format!("sum is {}", s)), &["sum"]);
+
+        assert_eq!(registry.resolve::<String>("text").ok().unwrap().take(), "sum is 2");
+    }
+
+    #[test]
+    fn should_return_not_registered_for_unknown_name() {
+        match Registry::new().resolve::<i32>("nope") {
+            Err(FactoryErrorKind::NotRegistered(e)) => assert_eq!(e.name, "nope"),
+            _ => panic!("Expected NotRegistered error!"),
+        }
+    }
+
+    #[test]
+    fn should_return_not_registered_for_unknown_argument() {
+        let mut registry = Registry::new();
+        registry.register("twice", metafactory(|a: i32| a * 2), &["missing"]);
+
+        match registry.resolve::<i32>("twice") {
+            Err(FactoryErrorKind::DefinitionError(e)) => {
+                assert_eq!(e.name, "twice");
+                match *e.error {
+                    FactoryErrorKind::NotRegistered(e) => assert_eq!(e.name, "missing"),
+                    _ => panic!("Expected NotRegistered error!"),
+                }
+            },
+            _ => panic!("Expected DefinitionError error!"),
+        }
+    }
+
+    #[test]
+    fn should_validate_argument_types() {
+        let mut registry = Registry::new();
+        registry.register("flag", metafactory(cloneable(true)), &[]);
+        registry.register("twice", metafactory(|a: i32| a * 2), &["flag"]);
+
+        match registry.resolve::<i32>("twice") {
+            Err(FactoryErrorKind::DefinitionError(e)) => match *e.error {
+                FactoryErrorKind::ArgTypeMismatch(e) => {
+                    assert_eq!(e.expected_type, TypeDef::of::<i32>());
+                    assert_eq!(e.argument_index, 0);
+                    assert_eq!(e.actual_type, TypeDef::of::<bool>());
+                },
+                _ => panic!("Expected ArgTypeMismatch error!"),
+            },
+            _ => panic!("Expected DefinitionError error!"),
+        }
+    }
+
+    #[test]
+    fn should_validate_argument_kinds() {
+        let mut registry = Registry::new();
+        registry.register("parsed", try_metafactory(|| "1".parse::<i32>()), &[]);
+        registry.register("twice", metafactory(|a: i32| a * 2), &["parsed"]);
+
+        match registry.resolve::<i32>("twice") {
+            Err(FactoryErrorKind::DefinitionError(e)) => match *e.error {
+                FactoryErrorKind::ArgKindMismatch(e) => {
+                    assert_eq!(e.produced_type, TypeDef::of::<i32>());
+                    assert_eq!(e.expected_kind, FactoryKind::Plain);
+                    assert_eq!(e.actual_kind, FactoryKind::fallible::<ParseIntError>());
+                },
+                _ => panic!("Expected ArgKindMismatch error!"),
+            },
+            _ => panic!("Expected DefinitionError error!"),
+        }
+    }
+
+    #[test]
+    fn should_validate_argument_count() {
+        let mut registry = Registry::new();
+        registry.register("one", metafactory(cloneable(1i32)), &[]);
+        registry.register("sum", metafactory(|a: i32, b: i32| a + b), &["one"]);
+
+        match registry.resolve::<i32>("sum") {
+            Err(FactoryErrorKind::DefinitionError(e)) => match *e.error {
+                FactoryErrorKind::ArgCountMismatch(e) => {
+                    assert_eq!(e.expected, 2);
+                    assert_eq!(e.specified, 1);
+                },
+                _ => panic!("Expected ArgCountMismatch error!"),
+            },
+            _ => panic!("Expected DefinitionError error!"),
+        }
+    }
+
+    #[test]
+    fn should_return_dependency_cycle() {
+        let mut registry = Registry::new();
+        registry.register("root", metafactory(|a: i32| a), &["a"]);
+        registry.register("a", metafactory(|b: i32| b), &["b"]);
+        registry.register("b", metafactory(|c: i32| c), &["c"]);
+        registry.register("c", metafactory(|a: i32| a), &["a"]);
+
+        match registry.resolve::<i32>("root") {
+            Err(FactoryErrorKind::DependencyCycle(e)) => {
+                assert_eq!(e.path, vec!["a", "b", "c", "a"]);
+            },
+            _ => panic!("Expected DependencyCycle error!"),
+        }
+    }
+
+    #[test]
+    fn should_return_type_mismatch_for_wrong_resolved_type() {
+        let mut registry = Registry::new();
+        registry.register("one", metafactory(cloneable(1i32)), &[]);
+
+        match registry.resolve::<bool>("one") {
+            Err(FactoryErrorKind::DefinitionError(e)) => match *e.error {
+                FactoryErrorKind::TypeMismatch(e) => {
+                    assert_eq!(e.expected_type, TypeDef::of::<bool>());
+                    assert_eq!(e.actual_type, TypeDef::of::<i32>());
+                },
+                _ => panic!("Expected TypeMismatch error!"),
+            },
+            _ => panic!("Expected DefinitionError error!"),
+        }
+    }
+
+    #[test]
+    fn should_wire_result_factory_into_fallible_definition() {
+        let mut registry = Registry::new();
+        registry.register("parsed", metafactory(|| "21".parse::<i32>().map_err(|e| e.to_string())), &[]);
+        registry.register("twice", try_metafactory(|a: i32| -> Result<i32, String> { Ok(a * 2) }), &["parsed"]);
+
+        let factory = registry.resolve_any("twice").ok().unwrap()
+            .as_try_factory_of::<i32, String>().unwrap();
+
+        assert_eq!(factory.try_take().ok().unwrap(), 42);
+    }
+}
@@ -0,0 +1,324 @@
+//! Implements per-scope instances.
+//!
+//! A `Scope` represents a unit of work, for example a request. Factories
+//! bound to the scope with `Factory::scoped` return the same instance for
+//! every `take` while the scope is entered, and build a new one the next
+//! time the scope is entered. The instances are dropped when the scope
+//! is left.
+//!
+//! Scoped factories are ordinary factories, so they can be plugged in
+//! as arguments anywhere in the factory tree, and all closures that use
+//! them during the same scope receive the same instance.
+//!
+//! Outside the scope, scoped factories produce a new value on every
+//! `take`, like any other factory.
+//!
+//! ```
+//! use std::rc::Rc;
+//! use std::cell::Cell;
+//! use metafactory::{ metafactory, AsFactoryExt };
+//! use metafactory::scope::Scope;
+//!
+//! fn main() {
+//!     let scope = Scope::new();
+//!     let requests = Rc::new(Cell::new(0));
+//!
+//!     let request_id = {
+//!         let requests = requests.clone();
+//!         metafactory(move || { requests.set(requests.get() + 1); requests.get() })
+//!             .new(Vec::new()).ok().unwrap()
+//!             .as_factory_of::<i32>().unwrap()
+//!             .scoped(&scope)
+//!     };
+//!
+//!     let pair = metafactory(|a: i32, b: i32| (a, b))
+//!         .new(vec![request_id.clone().into(), request_id.into()]).ok().unwrap()
+//!         .as_factory_of::<(i32, i32)>().unwrap();
+//!
+//!     {
+//!         let _request = scope.enter();
+//!         assert_eq!(pair.take(), (1, 1));
+//!         assert_eq!(pair.take(), (1, 1));
+//!     }
+//!     {
+//!         let _request = scope.enter();
+//!         assert_eq!(pair.take(), (2, 2));
+//!     }
+//! }
+//! ```
+//!
+//! `Scope` is not thread-safe: every thread should build its own factory
+//! tree and use its own scope.
+
+use std::any::Any;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::{ Factory, Getter };
+
+/// Instances stored by the entered scope, by scoped getter id.
+type Frame = HashMap<usize, Box<dyn Any>>;
+
+struct ScopeState {
+    /// Entered scopes, innermost last, with the id of their guard.
+    frames: Vec<(usize, Frame)>,
+    next_id: usize,
+}
+
+impl ScopeState {
+    fn new_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+/// Unit of work that shares instances of scoped factories.
+///
+/// Clones of the `Scope` refer to the same scope.
+#[derive(Clone)]
+pub struct Scope {
+    state: Rc<RefCell<ScopeState>>,
+}
+
+impl Default for Scope {
+    fn default() -> Scope {
+        Scope::new()
+    }
+}
+
+impl Scope {
+    /// Create a new scope that is not entered.
+    pub fn new() -> Scope {
+        Scope {
+            state: Rc::new(RefCell::new(ScopeState {
+                frames: Vec::new(),
+                next_id: 0,
+            })),
+        }
+    }
+
+    /// Enter the scope until the returned guard is dropped.
+    ///
+    /// Entering the scope again while it is already entered starts
+    /// a nested scope with its own instances. Instances of the outer scope
+    /// are used again when the nested one is left. Dropping the guard of
+    /// the outer scope leaves nested scopes too.
+    pub fn enter(&self) -> ScopeGuard {
+        let mut state = self.state.borrow_mut();
+        let frame_id = state.new_id();
+        state.frames.push((frame_id, HashMap::new()));
+        ScopeGuard {
+            state: self.state.clone(),
+            frame_id,
+        }
+    }
+
+    /// Check if the scope is currently entered.
+    pub fn is_active(&self) -> bool {
+        !self.state.borrow().frames.is_empty()
+    }
+
+    fn new_id(&self) -> usize {
+        self.state.borrow_mut().new_id()
+    }
+}
+
+/// Keeps the scope entered. The scope is left when this guard is dropped.
+pub struct ScopeGuard {
+    state: Rc<RefCell<ScopeState>>,
+    /// Id of the frame pushed by this guard.
+    frame_id: usize,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        // Instances are dropped after the borrow is released, in case
+        // their destructors use the scope. The frame is already gone if
+        // an outer guard was dropped first.
+        let frames = {
+            let mut state = self.state.borrow_mut();
+            match state.frames.iter().position(|&(id, _)| id == self.frame_id) {
+                Some(keep) => state.frames.split_off(keep),
+                None => Vec::new(),
+            }
+        };
+        drop(frames);
+    }
+}
+
+/// Getter that returns the same instance while the scope is entered.
+pub struct Scoped<T: 'static> {
+    id: usize,
+    source: Factory<T>,
+    state: Rc<RefCell<ScopeState>>,
+}
+
+impl<T: 'static + Clone> Scoped<T> {
+    /// Create a new `Scoped` getter that takes instances from source factory.
+    pub fn new(source: Factory<T>, scope: &Scope) -> Scoped<T> {
+        Scoped {
+            id: scope.new_id(),
+            source,
+            state: scope.state.clone(),
+        }
+    }
+}
+
+impl<T: 'static + Clone> Getter<T> for Scoped<T> {
+    fn take(&self) -> T {
+        let frame_id = {
+            let state = self.state.borrow();
+            let (frame_id, frame) = match state.frames.last() {
+                Some(&(frame_id, ref frame)) => (frame_id, frame),
+                None => return self.source.take(),
+            };
+            if let Some(value) = frame.get(&self.id).and_then(|value| value.downcast_ref::<T>()) {
+                return value.clone();
+            }
+            frame_id
+        };
+
+        // Source may take other factories of this scope, so the frames
+        // are not borrowed while it runs.
+        let value = self.source.take();
+        let mut state = self.state.borrow_mut();
+        if let Some(&mut (id, ref mut frame)) = state.frames.last_mut() {
+            if id == frame_id {
+                frame.insert(self.id, Box::new(value.clone()));
+            }
+        }
+        value
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(Scoped {
+            id: self.id,
+            source: self.source.clone(),
+            state: self.state.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use crate::{ ToMetaFactory, AsFactoryExt };
+    use crate::test_util::counting;
+    use super::Scope;
+
+    #[test]
+    fn should_return_same_instance_within_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        let _guard = scope.enter();
+        assert_eq!(factory.take(), 1);
+        assert_eq!(factory.clone().take(), 1);
+    }
+
+    #[test]
+    fn should_return_new_instance_in_next_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        {
+            let _guard = scope.enter();
+            assert_eq!(factory.take(), 1);
+        }
+        assert!(!scope.is_active());
+
+        let _guard = scope.enter();
+        assert_eq!(factory.take(), 2);
+        assert_eq!(factory.take(), 2);
+    }
+
+    #[test]
+    fn should_return_new_values_outside_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        assert_eq!(factory.take(), 1);
+        assert_eq!(factory.take(), 2);
+    }
+
+    #[test]
+    fn should_restore_outer_instance_after_nested_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        let _outer = scope.enter();
+        assert_eq!(factory.take(), 1);
+        {
+            let _inner = scope.enter();
+            assert_eq!(factory.take(), 2);
+        }
+        assert_eq!(factory.take(), 1);
+    }
+
+    #[test]
+    fn dropping_outer_guard_should_leave_nested_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        let outer = scope.enter();
+        assert_eq!(factory.take(), 1);
+        let inner = scope.enter();
+        assert_eq!(factory.take(), 2);
+
+        drop(outer);
+        assert!(!scope.is_active());
+        drop(inner);
+        assert!(!scope.is_active());
+
+        let _guard = scope.enter();
+        assert_eq!(factory.take(), 3);
+        assert_eq!(factory.take(), 3);
+    }
+
+    #[test]
+    fn stale_guard_should_not_leave_newer_scope() {
+        let scope = Scope::new();
+        let factory = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        let a = scope.enter();
+        let b = scope.enter();
+        drop(a);
+
+        let _c = scope.enter();
+        let _d = scope.enter();
+        assert_eq!(factory.take(), 1);
+
+        drop(b);
+        assert!(scope.is_active());
+        assert_eq!(factory.take(), 1);
+    }
+
+    #[test]
+    fn should_keep_separate_instances_for_different_factories() {
+        let scope = Scope::new();
+        let calls = Rc::new(Cell::new(0));
+        let first = counting(calls.clone()).scoped(&scope);
+        let second = counting(calls).scoped(&scope);
+
+        let _guard = scope.enter();
+        assert_eq!(first.take(), 1);
+        assert_eq!(second.take(), 2);
+        assert_eq!(first.take(), 1);
+    }
+
+    #[test]
+    fn closure_arguments_should_see_scoped_instance() {
+        let scope = Scope::new();
+        let id = counting(Rc::new(Cell::new(0))).scoped(&scope);
+
+        let pair = (|a: i32, b: i32| (a, b))
+            .to_metafactory()
+            .new(vec![id.clone().into(), id.into()]).ok().unwrap()
+            .as_factory_of::<(i32, i32)>().unwrap();
+
+        let _guard = scope.enter();
+        assert_eq!(pair.take(), (1, 1));
+    }
+}
@@ -0,0 +1,161 @@
+//! Implements opt-in memoizing getters.
+//!
+//! Factories normally produce a new value on every `take`. A factory
+//! wrapped in `Singleton` invokes its source only on the first `take`,
+//! and returns clones of that value afterwards. `RcSingleton` does the
+//! same, but returns `Rc` references to the single value, so the value
+//! itself does not need to be `Clone`.
+//!
+//! All clones of a singleton factory share the same value: `boxed_clone`
+//! copies the reference to the memoized slot, not the slot itself. To get
+//! an independent singleton, wrap the source factory again.
+//!
+//! ```
+//! use std::rc::Rc;
+//! use metafactory::{ metafactory, AsFactoryExt };
+//!
+//! fn main() {
+//!     let mut counter = 0;
+//!     let factory = metafactory(move || { counter += 1; counter })
+//!         .new(Vec::new()).ok().unwrap()
+//!         .as_factory_of::<i32>().unwrap();
+//!
+//!     let cached = factory.clone().cached();
+//!     assert_eq!(cached.take(), 1);
+//!     assert_eq!(cached.clone().take(), 1);
+//!
+//!     let shared = factory.shared();
+//!     assert!(Rc::ptr_eq(&shared.take(), &shared.clone().take()));
+//! }
+//! ```
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use crate::{ Factory, Getter };
+
+/// Getter that builds the value once and returns its clones.
+pub struct Singleton<T: 'static> {
+    source: Factory<T>,
+    value: Rc<RefCell<Option<T>>>,
+}
+
+impl<T: 'static + Clone> Singleton<T> {
+    /// Create a new `Singleton` that takes the value from source factory.
+    pub fn new(source: Factory<T>) -> Singleton<T> {
+        Singleton {
+            source,
+            value: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+impl<T: 'static + Clone> Getter<T> for Singleton<T> {
+    fn take(&self) -> T {
+        if let Some(ref value) = *self.value.borrow() {
+            return value.clone();
+        }
+
+        // The cell is released before calling the source, which may take
+        // other singletons.
+        let value = self.source.take();
+        *self.value.borrow_mut() = Some(value.clone());
+        value
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<T>> {
+        Box::new(Singleton {
+            source: self.source.clone(),
+            value: self.value.clone(),
+        })
+    }
+}
+
+/// Getter that builds the value once and returns `Rc` references to it.
+pub struct RcSingleton<T: 'static> {
+    source: Factory<T>,
+    value: Rc<RefCell<Option<Rc<T>>>>,
+}
+
+impl<T: 'static> RcSingleton<T> {
+    /// Create a new `RcSingleton` that takes the value from source factory.
+    pub fn new(source: Factory<T>) -> RcSingleton<T> {
+        RcSingleton {
+            source,
+            value: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+impl<T: 'static> Getter<Rc<T>> for RcSingleton<T> {
+    fn take(&self) -> Rc<T> {
+        if let Some(ref value) = *self.value.borrow() {
+            return value.clone();
+        }
+
+        let value = Rc::new(self.source.take());
+        *self.value.borrow_mut() = Some(value.clone());
+        value
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Getter<Rc<T>>> {
+        Box::new(RcSingleton {
+            source: self.source.clone(),
+            value: self.value.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use crate::test_util::counting;
+
+    #[test]
+    fn should_build_cached_value_once() {
+        let calls = Rc::new(Cell::new(0));
+        let factory = counting(calls.clone()).cached();
+
+        assert_eq!(calls.get(), 0);
+        assert_eq!(factory.take(), 1);
+        assert_eq!(factory.take(), 1);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn cached_factory_clones_should_share_value() {
+        let calls = Rc::new(Cell::new(0));
+        let factory = counting(calls.clone()).cached();
+        let factory_clone = factory.clone();
+
+        assert_eq!(factory_clone.take(), 1);
+        assert_eq!(factory.take(), 1);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn wrapping_again_should_create_new_singleton() {
+        let calls = Rc::new(Cell::new(0));
+        let source = counting(calls.clone());
+        let first = source.clone().cached();
+        let second = source.cached();
+
+        assert_eq!(first.take(), 1);
+        assert_eq!(second.take(), 2);
+        assert_eq!(first.take(), 1);
+    }
+
+    #[test]
+    fn should_share_single_rc_value() {
+        let calls = Rc::new(Cell::new(0));
+        let factory = counting(calls.clone()).shared();
+
+        let a = factory.take();
+        let b = factory.clone().take();
+
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(*a, 1);
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+//! This implements sync metafactory construction from cloneable value.
+
+use typedef::{ TypeDef };
+
+use crate::{ AnyFactory, FactoryType };
+use crate::from_clone::{ Cloneable };
+use crate::error::FactoryErrorKind;
+use crate::aggregate::Aggregate;
+use super::{ SyncMetaFactory, ToSyncMetaFactory, SendFactory, SendGetter };
+
+struct CloneableMetaFactory<T> {
+    pub value: T,
+}
+
+struct CloneableValue<T> {
+    pub value: T,
+}
+
+/// Creates `SyncMetaFactory` for cloneable value.
+impl<T: 'static + Clone + Send + Sync> ToSyncMetaFactory<Cloneable<T>> for Cloneable<T> {
+    fn to_sync_metafactory(self) -> Box<dyn SyncMetaFactory> {
+        Box::new(CloneableMetaFactory { value : self.value })
+    }
+}
+
+impl<T: 'static + Clone + Send + Sync> SyncMetaFactory for CloneableMetaFactory<T> {
+    fn get_type(&self) -> TypeDef {
+        TypeDef::of::<T>()
+    }
+
+    fn get_arg_types(&self) -> Vec<TypeDef> {
+        Vec::new()
+    }
+
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::send_of::<T>()
+    }
+
+    fn new(&self, _arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        Ok(
+            AnyFactory::from(SendFactory::new(
+                Box::new(CloneableValue::<T> { value: self.value.clone() })
+            ))
+        )
+    }
+
+    fn new_aggregate(&self) -> Aggregate<'static> {
+        Aggregate::new_send::<T>()
+    }
+}
+
+impl<T: 'static + Clone + Send + Sync> SendGetter<T> for CloneableValue<T> {
+    fn take(&self) -> T {
+        self.value.clone()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<T>> {
+        Box::new(CloneableValue::<T> { value: self.value.clone() })
+    }
+}
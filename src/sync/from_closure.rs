@@ -0,0 +1,310 @@
+//! This implements sync metafactory construction from a closure.
+//!
+//! The closure is shared between factories through `Arc`, so it must be
+//! `Fn + Send + Sync`. `FnMut + Send` closures can be wrapped with `locked`,
+//! which calls them under a `Mutex`. Like the non-sync variant, it supports
+//! up to 12 arguments.
+
+use std::marker::PhantomData;
+use std::sync::{ Arc, Mutex };
+
+use typedef::TypeDef;
+
+use crate::{ AnyFactory, ArgDescriptor, AsFactoryExt, FactoryKind, FactoryType };
+use crate::error::{ FactoryErrorKind, ArgCountMismatch, MissingArgument };
+use crate::aggregate::Aggregate;
+use super::{ SyncMetaFactory, ToSyncMetaFactory, SendFactory, SendGetter, sync_metafactory };
+
+/// `FnMut` closure that is called under a `Mutex`.
+pub struct Locked<F> {
+    closure: F,
+}
+
+/// Wrap `FnMut + Send` closure, so that it can be converted with
+/// `sync_metafactory`.
+///
+/// Calls from all factories and threads are serialized by a single `Mutex`.
+///
+/// ```
+/// use std::thread;
+/// use metafactory::AsFactoryExt;
+/// use metafactory::sync::{ sync_metafactory, locked };
+///
+/// let mut next = 0;
+/// let factory = sync_metafactory(locked(move || { next += 1; next }))
+///     .new(Vec::new()).ok().unwrap()
+///     .as_send_factory_of::<i32>().unwrap();
+///
+/// let other = factory.clone();
+/// thread::spawn(move || other.take()).join().unwrap();
+/// assert_eq!(factory.take(), 2);
+/// ```
+pub fn locked<F>(closure: F) -> Locked<F> {
+    Locked {
+        closure,
+    }
+}
+
+#[macro_use]
+mod macros {
+    macro_rules! sync_closure_impl(
+        ($ClosureScope:ident, $GetterScope:ident: $($_A:ident,$_AT:ty,$_a:ident)|*)
+        =>
+        (
+            /// Shared closure reference, used as `SyncMetaFactory`.
+            struct $ClosureScope<F, $($_A,)* T> {
+                closure: Arc<F>,
+                _marker: PhantomData<fn($($_AT),*) -> T>,
+            }
+
+            /// Contains references to parent factories, so they
+            /// can be invoked directly to get arguments for the closure.
+            struct $GetterScope<F, $($_A:'static,)* T:'static> {
+                // References to argument factories.
+                $(
+                    $_a: SendFactory<$_AT>,
+                )*
+                // Closure reference.
+                closure: Arc<F>,
+                _marker: PhantomData<fn() -> T>,
+            }
+
+            /// Implement `ToSyncMetaFactory` conversion for closures
+            /// Fn(A1, A2, ... AN) -> T + Send + Sync
+            impl<F, $($_A:'static,)* T:'static> ToSyncMetaFactory<fn($($_AT),*) -> T> for F
+                where F: Fn($($_AT),*) -> T + Send + Sync + 'static
+            {
+                fn to_sync_metafactory(self) -> Box<dyn SyncMetaFactory> {
+                    Box::new($ClosureScope::<F, $($_AT,)* T> {
+                        closure: Arc::new(self),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+
+            /// Implement `ToSyncMetaFactory` conversion for locked closures
+            /// FnMut(A1, A2, ... AN) -> T + Send
+            impl<F, $($_A:'static,)* T:'static> ToSyncMetaFactory<Locked<fn($($_AT),*) -> T>> for Locked<F>
+                where F: FnMut($($_AT),*) -> T + Send + 'static
+            {
+                fn to_sync_metafactory(self) -> Box<dyn SyncMetaFactory> {
+                    let closure = Mutex::new(self.closure);
+                    sync_metafactory(move |$($_a: $_AT),*| {
+                        // A panicking closure is called again as is, same as
+                        // a closure that returned an error.
+                        let mut closure = closure.lock().unwrap_or_else(|e| e.into_inner());
+                        (*closure)($($_a),*)
+                    })
+                }
+            }
+
+            impl<F, $($_A:'static,)* T:'static> SyncMetaFactory for $ClosureScope<F, $($_AT,)* T>
+                where F: Fn($($_AT),*) -> T + Send + Sync + 'static
+            {
+                fn get_type(&self) -> TypeDef {
+                    TypeDef::of::<T>()
+                }
+
+                fn get_arg_types(&self) -> Vec<TypeDef> {
+                    vec![$(TypeDef::of::<$_AT>()),*]
+                }
+
+                fn get_factory_type(&self) -> FactoryType {
+                    FactoryType::send_of::<T>()
+                }
+
+                fn get_args(&self) -> Vec<ArgDescriptor> {
+                    vec![$(ArgDescriptor::of::<$_AT>().with_kind(FactoryKind::Send)),*]
+                }
+
+                #[allow(unused_assignments, unused_mut, unused_variables)]
+                fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+                    let required_argc = count_exprs!($($_a),*);
+
+                    assert_arg_count!(required_argc, arg_getters.len());
+
+                    let mut getters = arg_getters.into_iter();
+                    let mut arg_index = 0;
+                    $(
+                        let $_a;
+                        {
+                            let maybe_factory = getters.next().unwrap();
+
+                            $_a = try_unwrap_factory!($_AT, maybe_factory, arg_index, FactoryKind::Send, as_send_factory_of);
+
+                            arg_index += 1;
+                        }
+                    )*
+
+                    let factory = AnyFactory::from(SendFactory::<T>::new(
+                        Box::new($GetterScope::<F, $($_AT,)* T> {
+                            $(
+                                $_a,
+                            )*
+                            closure: self.closure.clone(),
+                            _marker: PhantomData,
+                        })
+                    ));
+
+                    Ok(factory)
+                }
+
+                fn new_aggregate(&self) -> Aggregate<'static> {
+                    Aggregate::new_send::<T>()
+                }
+            }
+
+            impl<F, $($_A:'static,)* T: 'static> SendGetter<T> for $GetterScope<F, $($_AT,)* T>
+                where F: Fn($($_AT),*) -> T + Send + Sync + 'static
+            {
+                fn take(&self) -> T {
+                    (*self.closure)(
+                        $(
+                            self.$_a.take()
+                        ),*
+                    )
+                }
+
+                fn boxed_clone(&self) -> Box<dyn SendGetter<T>> {
+                    Box::new($GetterScope::<F, $($_AT,)* T> {
+                        $(
+                            $_a: self.$_a.clone(),
+                        )*
+                        closure: self.closure.clone(),
+                        _marker: PhantomData,
+                    })
+                }
+            }
+        )
+    );
+}
+
+sync_closure_impl!(
+    ClosureScope0, GetterScope0:
+);
+
+sync_closure_impl!(
+    ClosureScope1, GetterScope1:
+    A1, A1, a1
+);
+
+sync_closure_impl!(
+    ClosureScope2, GetterScope2:
+    A1, A1, a1 |
+    A2, A2, a2
+);
+
+sync_closure_impl!(
+    ClosureScope3, GetterScope3:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3
+);
+
+sync_closure_impl!(
+    ClosureScope4, GetterScope4:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4
+);
+
+sync_closure_impl!(
+    ClosureScope5, GetterScope5:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5
+);
+
+sync_closure_impl!(
+    ClosureScope6, GetterScope6:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6
+);
+
+sync_closure_impl!(
+    ClosureScope7, GetterScope7:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7
+);
+
+sync_closure_impl!(
+    ClosureScope8, GetterScope8:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7 |
+    A8, A8, a8
+);
+
+sync_closure_impl!(
+    ClosureScope9, GetterScope9:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7 |
+    A8, A8, a8 |
+    A9, A9, a9
+);
+
+sync_closure_impl!(
+    ClosureScope10, GetterScope10:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7 |
+    A8, A8, a8 |
+    A9, A9, a9 |
+    A10, A10, a10
+);
+
+sync_closure_impl!(
+    ClosureScope11, GetterScope11:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7 |
+    A8, A8, a8 |
+    A9, A9, a9 |
+    A10, A10, a10 |
+    A11, A11, a11
+);
+
+sync_closure_impl!(
+    ClosureScope12, GetterScope12:
+    A1, A1, a1 |
+    A2, A2, a2 |
+    A3, A3, a3 |
+    A4, A4, a4 |
+    A5, A5, a5 |
+    A6, A6, a6 |
+    A7, A7, a7 |
+    A8, A8, a8 |
+    A9, A9, a9 |
+    A10, A10, a10 |
+    A11, A11, a11 |
+    A12, A12, a12
+);
@@ -0,0 +1,355 @@
+//! Thread-safe counterparts of `Getter`, `Factory` and `MetaFactory`.
+//!
+//! Factories built from `MetaFactory` share their closures through `Rc`,
+//! so they can not leave the thread they were built on. This module
+//! contains a parallel family of traits that produce `SendFactory`
+//! instances: they are `Send + Sync`, and each clone can be moved to
+//! another thread.
+//!
+//! ```
+//! use std::thread;
+//! use metafactory::AsFactoryExt;
+//! use metafactory::sync::{ sync_metafactory, argless_as_send_factory };
+//!
+//! fn main() {
+//!     let meta_sum = sync_metafactory(|a: i32, b: i32| a + b);
+//!
+//!     let factory = meta_sum.new(vec![
+//!         argless_as_send_factory(|| 5),
+//!         argless_as_send_factory(|| 6),
+//!     ]).ok().unwrap().as_send_factory_of::<i32>().unwrap();
+//!
+//!     let handles: Vec<_> = (0..4)
+//!         .map(|_| {
+//!             let factory = factory.clone();
+//!             thread::spawn(move || factory.take())
+//!         })
+//!         .collect();
+//!
+//!     for handle in handles {
+//!         assert_eq!(handle.join().unwrap(), 11);
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+use typedef::{ TypeDef };
+use crate::{ AnyFactory, ArgDescriptor, FactoryKind, FactoryType };
+use crate::error::{ FactoryErrorKind };
+use crate::aggregate::{ Aggregate, MapAggregate };
+
+pub use self::singleton::{ SendSingleton, ArcSingleton };
+pub use self::from_closure::{ Locked, locked };
+
+mod from_clone;
+mod from_closure;
+mod singleton;
+
+/// Thread-safe gettable value trait.
+pub trait SendGetter<T>: Send + Sync {
+    /// Produce a new value.
+    fn take(&self) -> T;
+
+    /// Create a clone for this getter.
+    fn boxed_clone(&self) -> Box<dyn SendGetter<T>>;
+}
+
+/// A thread-safe factory proxy.
+///
+/// Same as `Factory`, but can be sent to and shared between threads.
+pub struct SendFactory<T: 'static> {
+    getter: Box<dyn SendGetter<T>>,
+}
+
+impl<T: 'static> SendFactory<T> {
+    /// Create a new `SendFactory`.
+    ///
+    /// Create a new factory from any type that implements `SendGetter` trait.
+    pub fn new(getter: Box<dyn SendGetter<T>>) -> SendFactory<T> {
+        SendFactory::<T> {
+            getter,
+        }
+    }
+
+    /// Get a new owned value.
+    pub fn take(&self) -> T {
+        self.getter.take()
+    }
+
+    /// Create a factory that builds the value once and returns its clones.
+    ///
+    /// All clones of the returned factory share the same value.
+    pub fn cached(self) -> SendFactory<T> where T: Clone + Send {
+        SendFactory::new(Box::new(SendSingleton::new(self)))
+    }
+
+    /// Create a factory that builds the value once and returns `Arc`
+    /// references to it.
+    ///
+    /// All clones of the returned factory share the same value.
+    pub fn shared(self) -> SendFactory<Arc<T>> where T: Send + Sync {
+        SendFactory::new(Box::new(ArcSingleton::new(self)))
+    }
+}
+
+impl<T: 'static> Clone for SendFactory<T> {
+    fn clone(&self) -> SendFactory<T> {
+        SendFactory::<T> {
+            getter: self.getter.boxed_clone(),
+        }
+    }
+}
+
+/// Thread-safe counterpart of `MetaFactory`.
+///
+/// Works the same way as `MetaFactory`, except that argument factories
+/// should be `SendFactory` instances, and the returned `AnyFactory` contains
+/// a `SendFactory`.
+#[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+pub trait SyncMetaFactory: Send + Sync {
+    fn get_type(&self) -> TypeDef;
+    fn get_arg_types(&self) -> Vec<TypeDef>;
+    fn new(&self, arg_getters: Vec<AnyFactory>) -> Result<AnyFactory, FactoryErrorKind>;
+    fn new_aggregate(&self) -> Aggregate<'static>;
+
+    /// Return the type of factories returned by `new`. Same as
+    /// `MetaFactory::get_factory_type`, except that it is a `SendFactory`.
+    fn get_factory_type(&self) -> FactoryType {
+        FactoryType::new(self.get_type(), FactoryKind::Send)
+    }
+
+    /// Describe every argument. Same as `MetaFactory::get_args`, except
+    /// that arguments expect `SendFactory`.
+    fn get_args(&self) -> Vec<ArgDescriptor> {
+        self.get_arg_types().into_iter()
+            .map(|arg_type| ArgDescriptor::required(arg_type).with_kind(FactoryKind::Send))
+            .collect()
+    }
+
+    /// Check if factories of specified types would be accepted by `new`.
+    /// Same as `MetaFactory::check_args`.
+    fn check_args(&self, arg_types: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+        crate::check_arg_types(&self.get_args(), arg_types)
+    }
+
+    /// Create a new factory, binding argument factories by argument names.
+    /// Same as `MetaFactory::new_named`.
+    fn new_named(&self, named_args: HashMap<String, AnyFactory>) -> Result<AnyFactory, FactoryErrorKind> {
+        self.new(crate::bind_named_args(&self.get_args(), named_args)?)
+    }
+
+    /// Check if factories of specified types could be used as arguments,
+    /// reporting all problems at once. Same as `MetaFactory::validate`.
+    fn validate(&self, arg_types: &[FactoryType]) -> Result<(), FactoryErrorKind> {
+        crate::validate_arg_types(&self.get_args(), arg_types)
+    }
+}
+
+impl dyn SyncMetaFactory {
+    /// Create an aggregate for the produced type that collects values into
+    /// `SendFactory` of container `C`. Same as `MetaFactory::new_aggregate_of`.
+    pub fn new_aggregate_of<C>(&self) -> Result<Aggregate<'static>, FactoryErrorKind>
+        where C: 'static + IntoIterator + FromIterator<<C as IntoIterator>::Item>
+    {
+        crate::expect_produced_type::<C::Item>(self.get_factory_type())?;
+        Ok(Aggregate::new_send_collect::<C::Item, C>())
+    }
+
+    /// Create a keyed aggregate for the produced type that collects values
+    /// into `SendFactory` of `HashMap<K, T>`.
+    ///
+    /// Returns `TypeMismatch` error if `T` is not the produced type.
+    pub fn new_map_aggregate<K, T>(&self) -> Result<MapAggregate<'static, K>, FactoryErrorKind>
+        where K: 'static + Clone + Eq + Hash + Send + Sync, T: 'static
+    {
+        crate::expect_produced_type::<T>(self.get_factory_type())?;
+        Ok(Aggregate::new_send_map::<K, T>())
+    }
+}
+
+/// Trait for values convertable to `SyncMetaFactory`.
+pub trait ToSyncMetaFactory<Args> {
+    /// Creates a `SyncMetaFactory` for this source.
+    fn to_sync_metafactory(self) -> Box<dyn SyncMetaFactory>;
+}
+
+/// Create a new `SyncMetaFactory` for any compatible value source.
+pub fn sync_metafactory<Args, T: ToSyncMetaFactory<Args>>(any: T) -> Box<dyn SyncMetaFactory> {
+    any.to_sync_metafactory()
+}
+
+/// Create a new `SyncMetaFactory` and return `SendFactory` in `AnyFactory` for source with no arguments.
+pub fn argless_as_send_factory<Args, T: ToSyncMetaFactory<Args>>(any: T) -> AnyFactory {
+    any.to_sync_metafactory().new(Vec::new()).ok().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{ HashMap, HashSet };
+    use std::thread;
+    use std::sync::Arc;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+    use crate::{ AsFactoryExt, cloneable };
+    use super::{ sync_metafactory, argless_as_send_factory, locked, SendFactory };
+
+    #[test]
+    fn factory_should_be_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SendFactory<String>>();
+    }
+
+    #[test]
+    fn cloned_factories_should_work_in_other_threads() {
+        let factory = sync_metafactory(|a: String, b: i32| format!("{}{}", a, b))
+            .new(vec![
+                argless_as_send_factory(cloneable("a".to_string())),
+                argless_as_send_factory(|| 1),
+            ]).ok().unwrap()
+            .as_send_factory_of::<String>().unwrap();
+
+        let handles: Vec<_> = (0..3)
+            .map(|_| {
+                let factory = factory.clone();
+                thread::spawn(move || factory.take())
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "a1");
+        }
+    }
+
+    #[test]
+    fn locked_closure_should_count_calls_from_all_threads() {
+        let mut count = 0usize;
+        let factory = sync_metafactory(locked(move |step: usize| { count += step; count }))
+            .new(vec![argless_as_send_factory(|| 1usize)]).ok().unwrap()
+            .as_send_factory_of::<usize>().unwrap();
+
+        let mut values: Vec<_> = (0..4)
+            .map(|_| {
+                let factory = factory.clone();
+                thread::spawn(move || (0..10).map(|_| factory.take()).collect::<Vec<_>>())
+            })
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        values.sort();
+
+        assert_eq!(values, (1..41).collect::<Vec<_>>());
+        assert_eq!(factory.take(), 41);
+    }
+
+    #[test]
+    fn locked_closure_should_recover_after_panic() {
+        let mut count = 0;
+        let factory = sync_metafactory(locked(move || {
+            count += 1;
+            if count == 1 {
+                panic!("first call fails");
+            }
+            count
+        }))
+            .new(Vec::new()).ok().unwrap()
+            .as_send_factory_of::<i32>().unwrap();
+
+        let failing = factory.clone();
+        assert!(thread::spawn(move || failing.take()).join().is_err());
+        assert_eq!(factory.take(), 2);
+    }
+
+    #[test]
+    fn should_not_downcast_regular_factory() {
+        let factory = crate::argless_as_factory(cloneable(5i32));
+
+        assert!(factory.as_send_factory_of::<i32>().is_none());
+    }
+
+    #[test]
+    fn should_build_shared_value_once_for_all_threads() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_by_closure = calls.clone();
+        let factory = argless_as_send_factory(move || calls_by_closure.fetch_add(1, Ordering::SeqCst) + 1)
+            .as_send_factory_of::<usize>().unwrap()
+            .shared();
+
+        let values: Vec<_> = (0..4)
+            .map(|_| {
+                let factory = factory.clone();
+                thread::spawn(move || factory.take())
+            })
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert!(values.iter().all(|v| Arc::ptr_eq(v, &values[0])));
+        assert_eq!(*values[0], 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(factory.clone().cached().take(), values[0]);
+    }
+
+    fn failing_once() -> SendFactory<i32> {
+        let calls = AtomicUsize::new(0);
+        argless_as_send_factory(move || {
+            if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first build fails");
+            }
+            5i32
+        })
+            .as_send_factory_of::<i32>().unwrap()
+    }
+
+    #[test]
+    fn should_retry_singleton_after_source_panic() {
+        let shared = failing_once().shared();
+        let failing = shared.clone();
+        assert!(thread::spawn(move || failing.take()).join().is_err());
+        assert_eq!(*shared.take(), 5);
+
+        let cached = failing_once().cached();
+        let failing = cached.clone();
+        assert!(thread::spawn(move || failing.take()).join().is_err());
+        assert_eq!(cached.take(), 5);
+    }
+
+    #[test]
+    fn should_aggregate_send_factories() {
+        let meta = sync_metafactory(|| true);
+        let aggregate = meta.new_aggregate();
+
+        let factory = aggregate
+            .new_factory(vec![
+                argless_as_send_factory(|| true),
+                argless_as_send_factory(cloneable(false)),
+            ]).ok().unwrap()
+            .as_send_factory_of::<Vec<bool>>().unwrap();
+
+        assert_eq!(thread::spawn(move || factory.take()).join().unwrap(), vec![true, false]);
+    }
+
+    #[test]
+    fn sync_metafactory_should_create_send_map_aggregate() {
+        let meta = sync_metafactory(|| 3u8);
+
+        let factory = meta.new_map_aggregate::<&'static str, u8>().ok().unwrap()
+            .new_factory(vec![("three", meta.new(Vec::new()).ok().unwrap())]).ok().unwrap()
+            .as_send_factory_of::<HashMap<&'static str, u8>>().unwrap();
+
+        assert_eq!(thread::spawn(move || factory.take()).join().unwrap()["three"], 3);
+        assert!(meta.new_map_aggregate::<&'static str, i8>().is_err());
+    }
+
+    #[test]
+    fn sync_metafactory_should_create_aggregate_for_container() {
+        let meta = sync_metafactory(|| 4u8);
+
+        let factory = meta.new_aggregate_of::<HashSet<u8>>().ok().unwrap()
+            .new_factory(vec![meta.new(Vec::new()).ok().unwrap(), meta.new(Vec::new()).ok().unwrap()]).ok().unwrap()
+            .as_send_factory_of::<HashSet<u8>>().unwrap();
+
+        assert_eq!(thread::spawn(move || factory.take()).join().unwrap().len(), 1);
+        assert!(meta.new_aggregate_of::<Vec<i8>>().is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Thread-safe memoizing getters.
+//!
+//! The value is built under a lock on the first `take`, so it is built
+//! only once even if several threads ask for it at the same time. The
+//! source factory must not take the same singleton again while building.
+//!
+//! If the source factory panics, the value stays unset and the next `take`
+//! tries to build it again.
+
+use std::sync::{ Arc, Mutex };
+
+use super::{ SendFactory, SendGetter };
+
+/// Thread-safe getter that builds the value once and returns its clones.
+pub struct SendSingleton<T: 'static> {
+    source: SendFactory<T>,
+    value: Arc<Mutex<Option<T>>>,
+}
+
+impl<T: 'static + Clone + Send> SendSingleton<T> {
+    /// Create a new `SendSingleton` that takes the value from source factory.
+    pub fn new(source: SendFactory<T>) -> SendSingleton<T> {
+        SendSingleton {
+            source,
+            value: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<T: 'static + Clone + Send> SendGetter<T> for SendSingleton<T> {
+    fn take(&self) -> T {
+        // A panicking source leaves the value unset, so poisoned lock is safe to reuse.
+        let mut value = self.value.lock().unwrap_or_else(|e| e.into_inner());
+        if value.is_none() {
+            *value = Some(self.source.take());
+        }
+        value.as_ref().unwrap().clone()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<T>> {
+        Box::new(SendSingleton {
+            source: self.source.clone(),
+            value: self.value.clone(),
+        })
+    }
+}
+
+/// Thread-safe getter that builds the value once and returns `Arc`
+/// references to it.
+pub struct ArcSingleton<T: 'static> {
+    source: SendFactory<T>,
+    value: Arc<Mutex<Option<Arc<T>>>>,
+}
+
+impl<T: 'static + Send + Sync> ArcSingleton<T> {
+    /// Create a new `ArcSingleton` that takes the value from source factory.
+    pub fn new(source: SendFactory<T>) -> ArcSingleton<T> {
+        ArcSingleton {
+            source,
+            value: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<T: 'static + Send + Sync> SendGetter<Arc<T>> for ArcSingleton<T> {
+    fn take(&self) -> Arc<T> {
+        // A panicking source leaves the value unset, so poisoned lock is safe to reuse.
+        let mut value = self.value.lock().unwrap_or_else(|e| e.into_inner());
+        if value.is_none() {
+            *value = Some(Arc::new(self.source.take()));
+        }
+        value.as_ref().unwrap().clone()
+    }
+
+    fn boxed_clone(&self) -> Box<dyn SendGetter<Arc<T>>> {
+        Box::new(ArcSingleton {
+            source: self.source.clone(),
+            value: self.value.clone(),
+        })
+    }
+}
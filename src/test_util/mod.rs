@@ -0,0 +1,14 @@
+//! Fixtures shared by unit tests of several modules.
+
+use std::rc::Rc;
+use std::cell::Cell;
+
+use crate::{ ToMetaFactory, Factory, AsFactoryExt };
+
+/// Factory that counts its calls in `calls` and returns the new count.
+pub fn counting(calls: Rc<Cell<i32>>) -> Factory<i32> {
+    (move || { calls.set(calls.get() + 1); calls.get() })
+        .to_metafactory()
+        .new(Vec::new()).ok().unwrap()
+        .as_factory_of::<i32>().unwrap()
+}